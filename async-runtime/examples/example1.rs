@@ -1,8 +1,8 @@
 use std::{thread::sleep, time::Duration};
 
 use async_runtime::executor::spawn_executor_thread;
-use async_runtime::temperature_sensor::{TemperatureSensor, TemperatureSensorFuture};
-use async_runtime::timer::TimerFuture;
+use async_runtime::sensor::Sensor;
+use async_runtime::temperature_sensor::TemperatureSensor;
 
 fn main() {
     // start the runtime thread
@@ -23,7 +23,7 @@ fn main() {
     spawner.spawn(async {
         let mut sensor = TemperatureSensor::new();
         loop {
-            let temps = sensor.read().unwrap().await;
+            let temps = sensor.read().await;
             sleep(Duration::from_secs(5));
 
             println!("received temps: {:#?}", temps);
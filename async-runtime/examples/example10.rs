@@ -0,0 +1,49 @@
+use std::time::Duration;
+
+use async_runtime::{
+    executor::Runtime,
+    registry::{RestartPolicy, TaskContext},
+    timer::TimerFuture,
+};
+
+// Takes 8 samples in total, across however many boots that needs
+async fn sampler(ctx: TaskContext) {
+    let mut taken: u32 = ctx.checkpoint().unwrap_or(0);
+    println!(
+        "boot {}: {} resuming at sample {}",
+        ctx.boot(),
+        ctx.name(),
+        taken
+    );
+
+    while taken < 8 {
+        TimerFuture::new(Duration::from_millis(250)).await;
+        taken += 1;
+        ctx.save_checkpoint(taken);
+    }
+    println!("boot {}: {} done", ctx.boot(), ctx.name());
+}
+
+fn main() {
+    let mut runtime = Runtime::new();
+
+    runtime.spawn_registered("provision", RestartPolicy::RunOnce, |ctx| async move {
+        println!("boot {}: {} running", ctx.boot(), ctx.name());
+    });
+    runtime.spawn_registered("sampler", RestartPolicy::FromCheckpoint, sampler);
+    runtime.spawn_registered("heartbeat", RestartPolicy::Always, |ctx| async move {
+        loop {
+            println!("boot {}: {}", ctx.boot(), ctx.name());
+            TimerFuture::new(Duration::from_millis(400)).await;
+        }
+    });
+
+    // Power fails every 900ms
+    for _ in 0..3 {
+        runtime.run_until(TimerFuture::new(Duration::from_millis(900)));
+        println!("-- power failure --");
+        runtime.reboot();
+    }
+    runtime.run_until(TimerFuture::new(Duration::from_millis(900)));
+    println!("booted {} times", runtime.boot_count() + 1);
+}
@@ -0,0 +1,45 @@
+use std::time::{Duration, Instant};
+
+use async_runtime::{
+    blocking::spawn_blocking,
+    executor::Runtime,
+    karma::{
+        Karma,
+        radio::{Radio, RadioFuture, RadioFutureCreateArg, RadioInputMsg},
+    },
+    timer::{MissedTickBehavior, interval, sleep_until, timeout},
+};
+
+fn main() {
+    let runtime = Runtime::new();
+
+    runtime.block_on(async {
+        let start = Instant::now();
+
+        // Sample every 500ms; one sample is slow, and the ticks it
+        // overlapped are skipped rather than fired back to back
+        let mut ticks = interval(Duration::from_millis(500));
+        ticks.set_missed_tick_behavior(MissedTickBehavior::Skip);
+        for sample in 0..5 {
+            let scheduled = ticks.tick().await;
+            println!("sample {} scheduled at {:?}", sample, scheduled - start);
+            if sample == 1 {
+                spawn_blocking(|| std::thread::sleep(Duration::from_millis(1200)))
+                    .await
+                    .unwrap();
+            }
+        }
+
+        sleep_until(start + Duration::from_secs(4)).await;
+
+        // A receive may never complete; give up after two seconds
+        let mut karma = Karma::new(Radio::new(1));
+        let msg = RadioInputMsg::Init;
+        RadioFuture::new(&mut karma, RadioFutureCreateArg::InputMsg(msg)).await;
+        let receive = RadioFuture::new(&mut karma, RadioFutureCreateArg::AwaitReceive);
+        match timeout(Duration::from_secs(2), receive).await {
+            Ok(packet) => println!("received {:?}", packet),
+            Err(elapsed) => println!("receive failed: {}", elapsed),
+        }
+    });
+}
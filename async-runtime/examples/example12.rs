@@ -0,0 +1,50 @@
+use std::{
+    pin::pin,
+    time::{Duration, Instant},
+};
+
+use async_runtime::{
+    executor::Runtime,
+    sync::mpsc,
+    timer::{TimerFuture, sleep},
+};
+use futures::future::{Either, select};
+
+const WATCHDOG: Duration = Duration::from_millis(800);
+
+fn main() {
+    let runtime = Runtime::new();
+    let spawner = runtime.spawner();
+
+    runtime.block_on(async move {
+        let (sender, mut receiver) = mpsc::channel(4);
+
+        // Packets arrive regularly, then the link stalls once
+        spawner.spawn(async move {
+            for (packet, gap) in [200, 300, 500, 1500, 200, 100].into_iter().enumerate() {
+                sleep(Duration::from_millis(gap)).await;
+                if sender.send(packet).await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        // One timer for the whole loop, kicked on every packet
+        let start = Instant::now();
+        let mut watchdog = pin!(TimerFuture::new(WATCHDOG));
+        loop {
+            let packet = Box::pin(receiver.recv());
+            match select(packet, watchdog.as_mut()).await {
+                Either::Left((Some(packet), _)) => {
+                    println!("{:?}: packet {}", start.elapsed(), packet);
+                    watchdog.as_mut().reset(Instant::now() + WATCHDOG);
+                }
+                Either::Left((None, _)) => break,
+                Either::Right((fired, _)) => {
+                    println!("{:?}: watchdog expired, resetting link", fired - start);
+                    watchdog.as_mut().reset(Instant::now() + WATCHDOG);
+                }
+            }
+        }
+    });
+}
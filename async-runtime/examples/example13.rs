@@ -0,0 +1,53 @@
+use std::fmt::Debug;
+
+use futures::StreamExt;
+
+use async_runtime::{
+    executor::Runtime,
+    sensor::{Accelerometer, HumiditySensor, LightSensor, Sensor},
+    temperature_sensor::TemperatureSensor,
+};
+
+// Written against the trait, so any sensor will do
+async fn report<S: Sensor>(name: &str, sensor: &mut S, samples: usize)
+where
+    S::Item: Debug,
+{
+    println!(
+        "{}: a sample every {:?} or so, in {}",
+        name,
+        sensor.sampling_period(),
+        sensor.units()
+    );
+    let mut taken = 0;
+    while taken < samples {
+        let batch = sensor.read().await;
+        taken += batch.len();
+        println!("  {:?}", batch);
+    }
+}
+
+fn main() {
+    let runtime = Runtime::new();
+
+    runtime.block_on(async {
+        let mut sensor = TemperatureSensor::new();
+
+        // One reading at a time
+        let readings: Vec<_> = sensor.by_ref().take(3).collect().await;
+        println!("first readings: {:?}", readings);
+
+        // Or everything that piled up since the last batch
+        let mut batches = sensor.batches();
+        while let Some(batch) = batches.next().await {
+            println!("batch of {}: {:?}", batch.len(), batch);
+            if batch.iter().any(|temp| *temp > 50.0) {
+                break;
+            }
+        }
+
+        report("humidity", &mut HumiditySensor::new(), 3).await;
+        report("light", &mut LightSensor::new(), 3).await;
+        report("accelerometer", &mut Accelerometer::new(), 3).await;
+    });
+}
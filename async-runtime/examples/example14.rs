@@ -0,0 +1,34 @@
+use async_runtime::{
+    executor::Runtime,
+    karma::Karma,
+    sensor::Sensor,
+    temperature_sensor::{TemperatureConfig, TemperatureInputMsg, TemperatureSensor},
+};
+
+fn main() {
+    let runtime = Runtime::new();
+
+    runtime.block_on(async {
+        let mut karma = Karma::new(TemperatureSensor::off(1));
+
+        // Calibrated, with half-degree resolution
+        let config = TemperatureConfig {
+            offset: -1.5,
+            resolution: 0.5,
+        };
+        karma.send(TemperatureInputMsg::Configure(config));
+        karma.send(TemperatureInputMsg::Start);
+        println!("before power loss: {:?}", karma.read().await);
+
+        // Each sample read is also raised as an output
+        let outputs = karma.peripheral().outputs();
+        println!("outputs: {:?}", outputs.try_iter().collect::<Vec<_>>());
+
+        // The sensor comes back off and unconfigured; replaying what was
+        // sent to it brings it back to sampling with the same settings
+        println!("sensor lost power; replaying its configuration");
+        karma.power_cycle();
+        karma.replay_support_queue().await.unwrap();
+        println!("after replay: {:?}", karma.read().await);
+    });
+}
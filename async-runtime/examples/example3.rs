@@ -0,0 +1,47 @@
+use std::time::Duration;
+
+use async_runtime::executor::spawn_executor_thread;
+use async_runtime::power::ConstantPowerModel;
+use async_runtime::task::panic_message;
+use async_runtime::timer::TimerFuture;
+
+fn main() {
+    // start the runtime thread
+    let (handle, spawner) = spawn_executor_thread();
+
+    let metrics = spawner.metrics();
+    // Roughly an MSP430 at 8 MHz
+    spawner.set_power_model(ConstantPowerModel {
+        active_mw: 7.2,
+        sleep_mw: 0.0025,
+        wakeup_uj: 0.05,
+    });
+    spawner.set_panic_hook(|id, payload| {
+        eprintln!("hook: {} failed with {:?}", id, panic_message(payload));
+    });
+    let inner_spawner = spawner.clone();
+    spawner.spawn(async move {
+        let timer_task = inner_spawner.spawn(async {
+            TimerFuture::new(Duration::from_secs(1)).await;
+            21
+        });
+        let panicking_task = inner_spawner.spawn(async {
+            panic!("this task fails");
+        });
+
+        let doubled = timer_task.await.unwrap() * 2;
+        println!("timer task returned {}", doubled);
+
+        let result: Result<(), _> = panicking_task.await;
+        println!(
+            "panicking task returned {:?}",
+            result.map_err(|e| e.to_string())
+        );
+    });
+
+    drop(spawner);
+
+    handle.join().unwrap();
+
+    println!("{}", metrics.snapshot().to_json());
+}
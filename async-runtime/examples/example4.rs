@@ -0,0 +1,41 @@
+use std::{thread::sleep, time::Duration};
+
+use async_runtime::{
+    karma::{
+        Karma,
+        radio::{Radio, RadioFuture, RadioFutureCreateArg, RadioInputMsg},
+    },
+    work_stealing::{default_worker_count, spawn_work_stealing_executor},
+};
+
+fn main() {
+    // start at least four executor threads, so the slow task can't stall the radios
+    let (handles, spawner) = spawn_work_stealing_executor(default_worker_count().max(4));
+
+    for id in 1..=3 {
+        spawner.spawn(async move {
+            let mut karma = Karma::new(Radio::new(id));
+
+            let msg = RadioInputMsg::Init;
+            let init_out = RadioFuture::new(&mut karma, RadioFutureCreateArg::InputMsg(msg)).await;
+            println!("radio {}: {:?}", id, init_out);
+
+            let received = RadioFuture::new(&mut karma, RadioFutureCreateArg::AwaitReceive).await;
+            println!("radio {}: {:?}", id, received);
+        });
+    }
+
+    // A slow, blocking task only stalls the worker it runs on
+    spawner.spawn(async {
+        for _ in 0..5 {
+            sleep(Duration::from_secs(2));
+            println!("slow task still running");
+        }
+    });
+
+    drop(spawner);
+
+    for handle in handles {
+        handle.join().unwrap();
+    }
+}
@@ -0,0 +1,52 @@
+use std::time::Duration;
+
+use async_runtime::{
+    executor::Runtime,
+    karma::{
+        Karma,
+        radio::{Radio, RadioFuture, RadioFutureCreateArg, RadioInputMsg},
+    },
+    timer::TimerFuture,
+};
+
+struct Cleanup(&'static str);
+
+impl Drop for Cleanup {
+    fn drop(&mut self) {
+        println!("cleaning up {}", self.0);
+    }
+}
+
+fn main() {
+    let runtime = Runtime::new();
+    let spawner = runtime.spawner();
+
+    runtime.block_on(async move {
+        // This task may wait for a packet for a long time
+        let receive_task = spawner.spawn(async {
+            let _cleanup = Cleanup("receive task");
+            let mut karma = Karma::new(Radio::new(1));
+
+            let msg = RadioInputMsg::Init;
+            RadioFuture::new(&mut karma, RadioFutureCreateArg::InputMsg(msg)).await;
+            RadioFuture::new(&mut karma, RadioFutureCreateArg::AwaitReceive).await
+        });
+
+        // Scoped background work: aborted as soon as the handle goes away
+        let background = spawner
+            .spawn(async {
+                let _cleanup = Cleanup("background task");
+                loop {
+                    TimerFuture::new(Duration::from_millis(500)).await;
+                    println!("background tick");
+                }
+            })
+            .abort_on_drop();
+
+        TimerFuture::new(Duration::from_secs(2)).await;
+        drop(background);
+
+        receive_task.abort();
+        println!("receive task: {:?}", receive_task.await);
+    });
+}
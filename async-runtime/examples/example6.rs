@@ -0,0 +1,42 @@
+use std::{cell::RefCell, rc::Rc, time::Duration};
+
+use async_runtime::{
+    karma::{
+        Karma,
+        radio::{Radio, RadioFuture, RadioFutureCreateArg, RadioInputMsg, RadioOutputMsg},
+    },
+    local_executor::LocalExecutor,
+    timer::TimerFuture,
+};
+
+fn main() {
+    let executor = LocalExecutor::new();
+    let spawner = executor.spawner();
+
+    // Plain `Rc<RefCell<_>>` application state, as on a single-core MCU
+    let packets: Rc<RefCell<Vec<Vec<u8>>>> = Rc::new(RefCell::new(vec![]));
+
+    let receiver_packets = packets.clone();
+    spawner.spawn(async move {
+        let mut karma = Karma::new(Radio::new(1));
+
+        let msg = RadioInputMsg::Init;
+        RadioFuture::new(&mut karma, RadioFutureCreateArg::InputMsg(msg)).await;
+
+        if let Some(RadioOutputMsg::DataReceived(data)) =
+            RadioFuture::new(&mut karma, RadioFutureCreateArg::AwaitReceive).await
+        {
+            receiver_packets.borrow_mut().push(data);
+        }
+    });
+
+    let count = executor.block_on(async move {
+        while packets.borrow().is_empty() {
+            TimerFuture::new(Duration::from_secs(1)).await;
+            println!("waiting for a packet...");
+        }
+        packets.borrow().len()
+    });
+
+    println!("received {} packet(s)", count);
+}
@@ -0,0 +1,60 @@
+use std::time::Duration;
+
+use async_runtime::{
+    executor::Runtime,
+    scope::{ScopeError, scope},
+    timer::TimerFuture,
+};
+use futures::FutureExt;
+
+struct Cleanup(usize);
+
+impl Drop for Cleanup {
+    fn drop(&mut self) {
+        println!("sensor {} stopped", self.0);
+    }
+}
+
+// Pretend to read a sensor; sensor 3 is broken
+async fn read_sensor(sensor: usize) -> Result<u32, String> {
+    let _cleanup = Cleanup(sensor);
+    TimerFuture::new(Duration::from_millis(200 * sensor as u64)).await;
+    if sensor == 3 {
+        return Err(format!("sensor {} did not respond", sensor));
+    }
+    Ok(sensor as u32 * 10)
+}
+
+fn main() {
+    let runtime = Runtime::new();
+
+    runtime.block_on(async {
+        // Children write straight into a buffer owned by this task
+        let mut readings = [0; 2];
+        let slots = &mut readings;
+        let result: Result<(), ScopeError<String>> = scope(move |s| {
+            for (i, slot) in slots.iter_mut().enumerate() {
+                s.spawn(async move {
+                    *slot = read_sensor(i + 1).await?;
+                    Ok(())
+                });
+            }
+            async { Ok(()) }.boxed()
+        })
+        .await;
+        println!("healthy sensors: {:?}, {:?}", result, readings);
+
+        // Sensor 3 fails, so sensor 4 is cancelled before it finishes
+        let result: Result<(), ScopeError<String>> = scope(|s| {
+            for sensor in 3..=4 {
+                s.spawn(async move { read_sensor(sensor).await.map(drop) });
+            }
+            async { Ok(()) }.boxed()
+        })
+        .await;
+        match result {
+            Ok(()) => println!("all sensors read"),
+            Err(error) => println!("{}", error),
+        }
+    });
+}
@@ -0,0 +1,50 @@
+use std::time::Duration;
+
+use async_runtime::{
+    coop::{LongPollPolicy, consume_budget, yield_now},
+    executor::Runtime,
+    timer::TimerFuture,
+};
+
+fn main() {
+    let mut runtime = Runtime::new();
+    runtime.set_long_poll_policy(LongPollPolicy {
+        threshold: Duration::from_millis(20),
+        log: true,
+    });
+    let spawner = runtime.spawner();
+    let metrics = runtime.metrics();
+
+    runtime.block_on(async move {
+        let ticker = spawner.spawn(async {
+            for tick in 0..3 {
+                TimerFuture::new(Duration::from_millis(100)).await;
+                println!("tick {}", tick);
+            }
+        });
+
+        // Busy, but yields regularly so the ticker stays on time
+        let cruncher = spawner.spawn(async {
+            let mut sum: u64 = 0;
+            for i in 0..3_000_000u64 {
+                sum = sum.wrapping_add(i * i);
+                if i % 10_000 == 0 {
+                    yield_now().await;
+                }
+                consume_budget().await;
+            }
+            sum
+        });
+
+        // Blocks the executor; flagged as a long poll
+        let sleeper = spawner.spawn(async {
+            std::thread::sleep(Duration::from_millis(50));
+        });
+
+        ticker.await.unwrap();
+        println!("sum: {}", cruncher.await.unwrap());
+        sleeper.await.unwrap();
+    });
+
+    println!("long polls: {}", metrics.snapshot().long_polls);
+}
@@ -0,0 +1,78 @@
+use std::{sync::Arc, time::Duration};
+
+use futures::future::{Either, select};
+
+use async_runtime::{
+    executor::Runtime,
+    karma::{
+        Karma,
+        radio::{Radio, RadioFuture, RadioFutureCreateArg, RadioInputMsg},
+    },
+    sensor::Sensor,
+    sync::{Mutex, Notify, mpsc, oneshot},
+    temperature_sensor::TemperatureSensor,
+    timer::TimerFuture,
+};
+
+fn main() {
+    let runtime = Runtime::new();
+    let spawner = runtime.spawner();
+
+    runtime.block_on(async move {
+        // One radio, shared by the tasks below
+        let mut karma = Karma::new(Radio::new(1));
+        for msg in [RadioInputMsg::Init, RadioInputMsg::StateTransmit] {
+            RadioFuture::new(&mut karma, RadioFutureCreateArg::InputMsg(msg)).await;
+        }
+        let radio = Arc::new(Mutex::new(karma));
+
+        // Samples flow from the sensor task to the uplink task
+        let (samples, mut received) = mpsc::channel(2);
+        spawner.spawn(async move {
+            let mut sensor = TemperatureSensor::new();
+            for _ in 0..2 {
+                let temps = sensor.read().await;
+                if samples.send(temps).await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        let (done, sent) = oneshot::channel();
+        let uplink_radio = radio.clone();
+        spawner.spawn(async move {
+            let mut count = 0;
+            while let Some(temps) = received.recv().await {
+                let packet = temps.iter().map(|temp| *temp as u8).collect();
+                let mut karma = uplink_radio.lock().await;
+                let msg = RadioInputMsg::Send(packet);
+                RadioFuture::new(&mut karma, RadioFutureCreateArg::InputMsg(msg)).await;
+                println!("uplink: sent {:?}", temps);
+                count += 1;
+            }
+            let _ = done.send(count);
+        });
+
+        let stop = Arc::new(Notify::new());
+        let beacon_stop = stop.clone();
+        let beacon = spawner.spawn(async move {
+            let mut beacons = 0;
+            loop {
+                let tick = TimerFuture::new(Duration::from_millis(700));
+                match select(beacon_stop.notified(), tick).await {
+                    Either::Left(_) => break beacons,
+                    Either::Right(_) => (),
+                }
+
+                let mut karma = radio.lock().await;
+                let msg = RadioInputMsg::Send(vec![0xbe, 0xac]);
+                RadioFuture::new(&mut karma, RadioFutureCreateArg::InputMsg(msg)).await;
+                beacons += 1;
+            }
+        });
+
+        println!("uplink sent {} packets", sent.await.unwrap());
+        stop.notify_one();
+        println!("beacon sent {} packets", beacon.await.unwrap());
+    });
+}
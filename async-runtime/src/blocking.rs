@@ -0,0 +1,129 @@
+use std::{
+    collections::VecDeque,
+    panic::{self, AssertUnwindSafe},
+    sync::{Arc, Condvar, Mutex, OnceLock},
+    thread,
+    time::Duration,
+};
+
+use crate::task::{JoinError, JoinHandle, JoinState, TaskId, join_pair};
+
+// Threads in the default pool used by `spawn_blocking`
+const DEFAULT_MAX_THREADS: usize = 16;
+// How long an idle pool thread waits for more work before exiting
+const KEEP_ALIVE: Duration = Duration::from_secs(10);
+
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+struct PoolState {
+    jobs: VecDeque<Job>,
+    threads: usize,
+    idle: usize,
+    // Idle threads already signalled for a queued job
+    notified: usize,
+}
+
+struct PoolShared {
+    state: Mutex<PoolState>,
+    available: Condvar,
+    max_threads: usize,
+}
+
+/// A bounded pool of threads for blocking work. Threads are started on
+/// demand, reused for later jobs, and exit after sitting idle for a while;
+/// once all of them are busy, jobs queue up.
+#[derive(Clone)]
+pub struct BlockingPool {
+    shared: Arc<PoolShared>,
+}
+
+impl BlockingPool {
+    pub fn new(max_threads: usize) -> Self {
+        assert!(max_threads > 0, "a blocking pool needs at least one thread");
+
+        BlockingPool {
+            shared: Arc::new(PoolShared {
+                state: Mutex::new(PoolState {
+                    jobs: VecDeque::new(),
+                    threads: 0,
+                    idle: 0,
+                    notified: 0,
+                }),
+                available: Condvar::new(),
+                max_threads,
+            }),
+        }
+    }
+
+    /// Runs `f` on a pool thread. Awaiting the returned handle does not
+    /// block the executor; the awaiting task is woken once `f` returns.
+    /// Jobs cannot be aborted: `abort` on the handle does nothing, and `f`
+    /// runs to completion even if the handle is dropped.
+    pub fn spawn<T, F>(&self, f: F) -> JoinHandle<T>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let (notifier, mut handle) = join_pair(TaskId::next());
+        handle.task = Arc::<Mutex<JoinState<T>>>::downgrade(&handle.shared_state);
+        let job = Box::new(move || {
+            let result = panic::catch_unwind(AssertUnwindSafe(f)).map_err(JoinError::Panicked);
+            notifier.complete(result);
+        });
+
+        let mut state = self.shared.state.lock().unwrap();
+        state.jobs.push_back(job);
+        if state.idle > state.notified {
+            state.notified += 1;
+            self.shared.available.notify_one();
+        } else if state.threads < self.shared.max_threads {
+            state.threads += 1;
+            let shared = self.shared.clone();
+            thread::spawn(move || worker_loop(shared));
+        }
+
+        handle
+    }
+
+    /// Threads currently running, busy or idle.
+    pub fn threads(&self) -> usize {
+        self.shared.state.lock().unwrap().threads
+    }
+}
+
+fn worker_loop(shared: Arc<PoolShared>) {
+    let mut state = shared.state.lock().unwrap();
+    loop {
+        if let Some(job) = state.jobs.pop_front() {
+            drop(state);
+            job();
+            state = shared.state.lock().unwrap();
+            continue;
+        }
+
+        state.idle += 1;
+        let (next, timeout) = shared.available.wait_timeout(state, KEEP_ALIVE).unwrap();
+        state = next;
+        state.idle -= 1;
+
+        if state.notified > 0 {
+            state.notified -= 1;
+        } else if timeout.timed_out() && state.jobs.is_empty() {
+            state.threads -= 1;
+            return;
+        }
+    }
+}
+
+/// Runs blocking `f` (long computations, `thread::sleep`, blocking I/O) on
+/// a shared pool of threads instead of the executor's.
+pub fn spawn_blocking<T, F>(f: F) -> JoinHandle<T>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    static POOL: OnceLock<BlockingPool> = OnceLock::new();
+
+    POOL.get_or_init(|| BlockingPool::new(DEFAULT_MAX_THREADS))
+        .spawn(f)
+}
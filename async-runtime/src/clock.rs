@@ -0,0 +1,137 @@
+use std::{
+    sync::{Arc, Mutex, OnceLock, RwLock},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use crate::timer;
+
+/// A source of the current time. Timers, deadlines and labeled values all
+/// read the clock installed with `set_clock`.
+pub trait Clock: Send + Sync {
+    fn now(&self) -> Instant;
+
+    /// How long to wait, in real time, for the clock to reach `deadline`;
+    /// `None` if the clock only moves when told to.
+    fn real_time_until(&self, deadline: Instant) -> Option<Duration>;
+}
+
+/// The operating system's monotonic clock. Used unless another clock is
+/// installed.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn real_time_until(&self, deadline: Instant) -> Option<Duration> {
+        Some(deadline.saturating_duration_since(Instant::now()))
+    }
+}
+
+/// A clock that stands still until advanced, for driving timers and
+/// expiry deterministically. Clones share the same time.
+#[derive(Clone, Debug)]
+pub struct VirtualClock {
+    now: Arc<Mutex<Instant>>,
+}
+
+impl VirtualClock {
+    /// A clock stopped at the current system time.
+    pub fn new() -> Self {
+        VirtualClock {
+            now: Arc::new(Mutex::new(Instant::now())),
+        }
+    }
+
+    /// Moves the clock forward by `duration`. Timers that are due by then
+    /// have been woken when this returns.
+    pub fn advance(&self, duration: Duration) {
+        *self.now.lock().unwrap() += duration;
+        timer::clock_changed();
+    }
+}
+
+impl Default for VirtualClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for VirtualClock {
+    fn now(&self) -> Instant {
+        *self.now.lock().unwrap()
+    }
+
+    fn real_time_until(&self, _deadline: Instant) -> Option<Duration> {
+        None
+    }
+}
+
+static CLOCK: RwLock<Option<Arc<dyn Clock>>> = RwLock::new(None);
+
+/// Installs the clock used by the whole process. Meant to be called once,
+/// before any timer is created: timers already registered keep deadlines
+/// taken from the previous clock.
+pub fn set_clock(clock: impl Clock + 'static) {
+    *CLOCK.write().unwrap() = Some(Arc::new(clock));
+    timer::clock_changed();
+}
+
+/// The current time on the installed clock.
+pub fn now() -> Instant {
+    match &*CLOCK.read().unwrap() {
+        Some(clock) => clock.now(),
+        None => Instant::now(),
+    }
+}
+
+pub(crate) fn real_time_until(deadline: Instant) -> Option<Duration> {
+    match &*CLOCK.read().unwrap() {
+        Some(clock) => clock.real_time_until(deadline),
+        None => SystemClock.real_time_until(deadline),
+    }
+}
+
+/// A source of wall-clock time, as time since the Unix epoch. Unlike an
+/// `Instant`, a timestamp stays meaningful across restarts.
+pub trait WallClock: Send + Sync {
+    fn timestamp(&self) -> Duration;
+}
+
+static WALL_CLOCK: RwLock<Option<Arc<dyn WallClock>>> = RwLock::new(None);
+
+/// Installs the wall clock used by the whole process, e.g. a battery-backed
+/// RTC.
+pub fn set_wall_clock(clock: impl WallClock + 'static) {
+    *WALL_CLOCK.write().unwrap() = Some(Arc::new(clock));
+}
+
+/// The current wall-clock time. Without an installed wall clock this is
+/// the system time, moved along by the installed `Clock`.
+pub fn timestamp() -> Duration {
+    if let Some(clock) = &*WALL_CLOCK.read().unwrap() {
+        return clock.timestamp();
+    }
+
+    // The system time when the clock was first read, and that reading
+    static ANCHOR: OnceLock<(Duration, Instant)> = OnceLock::new();
+    let (system_time, instant) = *ANCHOR.get_or_init(|| {
+        let system_time = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+        (system_time, now())
+    });
+    system_time + now().saturating_duration_since(instant)
+}
+
+/// The instant at which the wall clock will read `timestamp`, assuming it
+/// runs at the same rate as the installed `Clock`.
+pub fn instant_at(timestamp: Duration) -> Instant {
+    let current = self::timestamp();
+    let now = now();
+    if timestamp >= current {
+        now + (timestamp - current)
+    } else {
+        now.checked_sub(current - timestamp).unwrap_or(now)
+    }
+}
@@ -0,0 +1,107 @@
+use std::{
+    cell::Cell,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use futures::future::poll_fn;
+
+// Runtime futures a task may complete in one poll before it is made to yield
+const BUDGET: u32 = 128;
+
+thread_local! {
+    // Budget left for the task being polled on this thread; `None` outside of a task
+    static BUDGET_LEFT: Cell<Option<u32>> = const { Cell::new(None) };
+}
+
+/// What the executor does about a single poll that runs for too long,
+/// e.g. because the task called `std::thread::sleep` or did heavy work
+/// without yielding.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LongPollPolicy {
+    /// Polls longer than this are counted in the task's `long_polls` metric.
+    pub threshold: Duration,
+    /// Also print each long poll, with the task's id, to stderr.
+    pub log: bool,
+}
+
+impl Default for LongPollPolicy {
+    fn default() -> Self {
+        LongPollPolicy {
+            threshold: Duration::from_millis(50),
+            log: false,
+        }
+    }
+}
+
+/// Restores the previous budget when dropped.
+pub(crate) struct BudgetGuard {
+    previous: Option<u32>,
+}
+
+impl Drop for BudgetGuard {
+    fn drop(&mut self) {
+        BUDGET_LEFT.set(self.previous);
+    }
+}
+
+/// Gives the current thread a fresh budget; called before each task poll.
+pub(crate) fn budget() -> BudgetGuard {
+    BudgetGuard {
+        previous: BUDGET_LEFT.replace(Some(BUDGET)),
+    }
+}
+
+/// Polls a runtime future under the current task's budget: once the budget
+/// is used up, the task is rescheduled instead of polled further, so a
+/// task whose futures are always ready still lets others run.
+pub(crate) fn cooperative<T>(
+    cx: &mut Context<'_>,
+    poll: impl FnOnce(&mut Context<'_>) -> Poll<T>,
+) -> Poll<T> {
+    if BUDGET_LEFT.get() == Some(0) {
+        cx.waker().wake_by_ref();
+        return Poll::Pending;
+    }
+
+    let result = poll(cx);
+    // Only completing counts against the budget
+    if result.is_ready()
+        && let Some(left) = BUDGET_LEFT.get()
+    {
+        BUDGET_LEFT.set(Some(left.saturating_sub(1)));
+    }
+    result
+}
+
+/// Uses up one unit of the current task's budget, yielding to other tasks
+/// once it is exhausted. For loops that do a lot of work without awaiting
+/// anything that could be pending.
+pub async fn consume_budget() {
+    poll_fn(|cx| cooperative(cx, |_| Poll::Ready(()))).await
+}
+
+/// Yields to the executor once, letting other ready tasks run before the
+/// current one continues.
+pub fn yield_now() -> YieldNow {
+    YieldNow { yielded: false }
+}
+
+pub struct YieldNow {
+    yielded: bool,
+}
+
+impl Future for YieldNow {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        if self.yielded {
+            return Poll::Ready(());
+        }
+
+        self.yielded = true;
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    }
+}
@@ -0,0 +1,105 @@
+use std::{
+    pin::Pin,
+    sync::{Arc, Weak},
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use serde::Serialize;
+
+use crate::task::{Task, with_current};
+
+/// How well an executor kept up with task deadlines.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct DeadlineStats {
+    /// Polls of tasks that had a deadline
+    pub polls: u64,
+    /// Of those, polls that started after the deadline had passed
+    pub missed: u64,
+    /// Largest delay past a deadline seen at the start of a poll
+    pub max_lateness: Duration,
+}
+
+impl DeadlineStats {
+    pub(crate) fn record(&mut self, deadline: Instant, now: Instant) {
+        self.polls += 1;
+        if now > deadline {
+            self.missed += 1;
+            self.max_lateness = self.max_lateness.max(now - deadline);
+        }
+    }
+}
+
+/// Sets (or clears) the deadline of the current task. Does nothing when
+/// called outside of a task.
+pub fn set_deadline(deadline: Option<Instant>) {
+    with_current(|task| task.set_deadline(deadline));
+}
+
+/// The deadline of the current task, if it has one.
+pub fn deadline() -> Option<Instant> {
+    with_current(|task| task.deadline()).flatten()
+}
+
+/// Runs `future` with the current task's deadline tightened to `deadline`
+/// (if that is earlier), restoring the previous deadline once it completes
+/// or is dropped. Wrapping an await this way lets the EDF queue see the
+/// deadline whenever the task is woken. A `None` deadline leaves the task's
+/// deadline alone.
+pub fn with_deadline<F: Future>(
+    deadline: impl Into<Option<Instant>>,
+    future: F,
+) -> WithDeadline<F> {
+    WithDeadline {
+        future: Box::pin(future),
+        deadline: deadline.into(),
+        restore: None,
+    }
+}
+
+pub struct WithDeadline<F> {
+    future: Pin<Box<F>>,
+    deadline: Option<Instant>,
+
+    // The task whose deadline we tightened, and what it was before
+    restore: Option<(Weak<Task>, Option<Instant>)>,
+}
+
+impl<F> WithDeadline<F> {
+    fn restore(&mut self) {
+        if let Some((task, previous)) = self.restore.take()
+            && let Some(task) = task.upgrade()
+        {
+            task.set_deadline(previous);
+        }
+    }
+}
+
+impl<F: Future> Future for WithDeadline<F> {
+    type Output = F::Output;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        if self.restore.is_none()
+            && let Some(deadline) = self.deadline
+        {
+            self.restore = with_current(|task| {
+                let previous = task.deadline();
+                let tightened = previous.map_or(deadline, |previous| previous.min(deadline));
+                task.set_deadline(Some(tightened));
+                (Arc::downgrade(task), previous)
+            });
+        }
+
+        let result = self.future.as_mut().poll(cx);
+        if result.is_ready() {
+            self.restore();
+        }
+        result
+    }
+}
+
+impl<F> Drop for WithDeadline<F> {
+    fn drop(&mut self) {
+        self.restore();
+    }
+}
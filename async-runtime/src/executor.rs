@@ -1,54 +1,485 @@
 use std::{
+    cmp::Ordering,
+    collections::BinaryHeap,
+    panic::{self, AssertUnwindSafe},
     sync::{
-        mpsc::{sync_channel, Receiver}, Arc
+        Arc, Mutex,
+        mpsc::{Receiver, SyncSender, TrySendError, sync_channel},
     },
     task::{Context, Poll},
     thread::{self, JoinHandle},
+    time::{Duration, Instant},
 };
 
-use futures::task::{ArcWake, waker_ref};
+use futures::{FutureExt, task::waker_ref};
 
-use crate::task::{Spawner, Task};
+use crate::clock;
+use crate::coop::{self, LongPollPolicy};
+use crate::metrics::{Metrics, MetricsHandle, TaskStatus};
+use crate::power::{Activity, PowerModel};
+use crate::registry::{Registry, RestartPolicy, TaskContext};
+use crate::task::{self, JoinError, PanicPayload, RunQueue, Scheduler, Spawner, Task, TaskId};
 
 const MAX_TASKS: usize = 10_000;
 
+/// How the executor picks the next ready task.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SchedulingPolicy {
+    /// In the order tasks were woken.
+    #[default]
+    Fifo,
+    /// Earliest deadline first; tasks without a deadline run after all
+    /// tasks with one, in wake order.
+    EarliestDeadlineFirst,
+}
+
 pub struct Executor {
     receiver: Receiver<Arc<Task>>,
+
+    policy: SchedulingPolicy,
+    // Tasks drained from the channel, ordered by deadline (EDF only)
+    ready: BinaryHeap<ReadyTask>,
+    // Tie-breaker that keeps equal deadlines in wake order
+    next_seq: u64,
+
+    activity: Activity,
+}
+
+struct ReadyTask {
+    deadline: Option<Instant>,
+    seq: u64,
+    task: Arc<Task>,
+}
+
+impl ReadyTask {
+    fn key(&self) -> (bool, Option<Instant>, u64) {
+        (self.deadline.is_none(), self.deadline, self.seq)
+    }
+}
+
+impl PartialEq for ReadyTask {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl Eq for ReadyTask {}
+
+impl PartialOrd for ReadyTask {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
 }
 
-impl ArcWake for Task {
-    fn wake_by_ref(arc_self: &Arc<Self>) {
-        arc_self.sender.try_send(arc_self.clone()).unwrap();
+impl Ord for ReadyTask {
+    // Reversed, so that the max-heap pops the earliest deadline
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.key().cmp(&self.key())
+    }
+}
+
+struct ChannelQueue {
+    // Taken on close, which disconnects the executor's receiver
+    sender: Mutex<Option<SyncSender<Arc<Task>>>>,
+}
+
+impl RunQueue for ChannelQueue {
+    fn push(&self, task: Arc<Task>) {
+        // After shutdown there is nobody left to run the task; drop the wake
+        if let Some(sender) = &*self.sender.lock().unwrap() {
+            match sender.try_send(task) {
+                Ok(()) | Err(TrySendError::Disconnected(_)) => (),
+                // Cancelled tasks still queued can use up the room
+                Err(TrySendError::Full(task)) => task.wake_dropped(),
+            }
+        }
+    }
+
+    fn capacity(&self) -> Option<usize> {
+        Some(MAX_TASKS)
+    }
+
+    fn close(&self) {
+        self.sender.lock().unwrap().take();
     }
 }
 
 fn build_runtime() -> (Executor, Spawner) {
     let (sender, receiver) = sync_channel(MAX_TASKS);
-    (Executor { receiver }, Spawner { sender })
+    let scheduler = Scheduler::new(
+        ChannelQueue {
+            sender: Mutex::new(Some(sender)),
+        },
+        Metrics::new(),
+    );
+    let executor = Executor {
+        receiver,
+        policy: SchedulingPolicy::default(),
+        ready: BinaryHeap::new(),
+        next_seq: 0,
+        activity: Activity::new(scheduler.metrics.clone()),
+    };
+    (executor, Spawner::new(scheduler))
 }
 
-pub fn spawn_executor_thread() -> (JoinHandle<()>, Spawner) {
-    let (executor, spawner) = build_runtime();
-
-    let handle = thread::spawn(move || {
-        while let Ok(task) = executor.receiver.recv() {
-            let mut future_slot = task.future.lock().unwrap();
-
-            if let Some(mut future) = future_slot.take() {
-                let waker = waker_ref(&task);
-                let mut context = Context::from_waker(&waker);
-
-                // call poll
-                let result = future.as_mut().poll(&mut context);
-                match result {
-                    Poll::Ready(_) => println!("We finished a task! Yippee!!"),
-                    Poll::Pending => {
-                        *future_slot = Some(future);
-                    }
+fn record_poll(task: &Task, duration: Duration) {
+    task.counters.polled(duration);
+
+    let policy = *task.scheduler.long_poll_policy.lock().unwrap();
+    if duration > policy.threshold {
+        task.counters.long_poll();
+        if policy.log {
+            eprintln!(
+                "{} blocked the executor for {:?} in a single poll (threshold {:?})",
+                task.id(),
+                duration,
+                policy.threshold
+            );
+        }
+    }
+}
+
+// Polls a task once, putting its future back if it is still pending.
+pub(crate) fn poll_task(task: &Arc<Task>) {
+    let mut future_slot = task.future.lock().unwrap();
+    if !task.start_running() {
+        return;
+    }
+
+    if task.is_aborted() {
+        // Drop the future now, running its destructors; its join handle
+        // reports the task as cancelled
+        let future = future_slot.take();
+        drop(future_slot);
+        drop(future);
+        task.finish(TaskStatus::Cancelled);
+        return;
+    }
+
+    if let Some(deadline) = task.deadline() {
+        task.scheduler
+            .metrics
+            .record_deadline(deadline, clock::now());
+    }
+
+    if let Some(mut future) = future_slot.take() {
+        let _enter = task.enter();
+        let _budget = coop::budget();
+        let waker = waker_ref(task);
+        let mut context = Context::from_waker(&waker);
+
+        // call poll, isolating the rest of the executor from a panic
+        let started = Instant::now();
+        let result = panic::catch_unwind(AssertUnwindSafe(|| future.as_mut().poll(&mut context)));
+        record_poll(task, started.elapsed());
+
+        match result {
+            Ok(Poll::Ready(_)) => {
+                drop(future_slot);
+                task.finish(TaskStatus::Completed);
+            }
+            Err(payload) => {
+                drop(future_slot);
+                task.fail(future, payload);
+            }
+            Ok(Poll::Pending) => {
+                *future_slot = Some(future);
+                if task.stop_running() {
+                    drop(future_slot);
+                    task.scheduler.schedule(task.clone());
                 }
             }
         }
-    });
+    }
+}
+
+impl Executor {
+    fn push_ready(&mut self, task: Arc<Task>) {
+        self.ready.push(ReadyTask {
+            deadline: task.deadline(),
+            seq: self.next_seq,
+            task,
+        });
+        self.next_seq += 1;
+    }
+
+    // Blocks until a task arrives, counting the wait as idle time
+    fn recv(&mut self) -> Option<Arc<Task>> {
+        if let Ok(task) = self.receiver.try_recv() {
+            return Some(task);
+        }
+
+        self.activity.sleep(|| self.receiver.recv().ok())
+    }
+
+    // Blocks until a task is ready; `None` once the executor has shut down
+    fn next_task(&mut self) -> Option<Arc<Task>> {
+        match self.policy {
+            SchedulingPolicy::Fifo => self.recv(),
+            SchedulingPolicy::EarliestDeadlineFirst => {
+                if self.ready.is_empty() {
+                    let task = self.recv()?;
+                    self.push_ready(task);
+                }
+                while let Ok(task) = self.receiver.try_recv() {
+                    self.push_ready(task);
+                }
+                self.ready.pop().map(|ready| ready.task)
+            }
+        }
+    }
+
+    // Runs tasks until every spawner is dropped and no task is left
+    fn run(&mut self) {
+        self.activity.resume();
+        while let Some(task) = self.next_task() {
+            poll_task(&task);
+        }
+        self.activity.suspend();
+    }
+}
+
+/// Runs the executor on a new thread. The thread exits once the returned
+/// spawner (and all its clones) are dropped and every task has finished.
+pub fn spawn_executor_thread() -> (JoinHandle<()>, Spawner) {
+    let (mut executor, spawner) = build_runtime();
+
+    let handle = thread::spawn(move || executor.run());
 
     (handle, spawner)
 }
+
+/// What `Runtime::block_on` does with tasks still running once the root
+/// future has completed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ShutdownMode {
+    /// Keep running until every task has finished and every spawner is dropped.
+    #[default]
+    WaitForTasks,
+    /// Cancel the remaining tasks and return immediately.
+    CancelTasks,
+}
+
+/// A single-threaded runtime that drives a root future on the current thread.
+pub struct Runtime {
+    executor: Executor,
+    spawner: Spawner,
+    shutdown_mode: ShutdownMode,
+    registry: Registry,
+}
+
+impl Runtime {
+    pub fn new() -> Self {
+        let (executor, spawner) = build_runtime();
+
+        Runtime {
+            executor,
+            spawner,
+            shutdown_mode: ShutdownMode::default(),
+            registry: Registry::new(),
+        }
+    }
+
+    pub fn set_shutdown_mode(&mut self, shutdown_mode: ShutdownMode) {
+        self.shutdown_mode = shutdown_mode;
+    }
+
+    pub fn set_scheduling_policy(&mut self, policy: SchedulingPolicy) {
+        self.executor.policy = policy;
+    }
+
+    /// Replaces the hook called whenever a task panics. The default one
+    /// logs the task and panic message to stderr.
+    pub fn set_panic_hook(&mut self, hook: impl Fn(TaskId, &PanicPayload) + Send + Sync + 'static) {
+        self.spawner.set_panic_hook(hook);
+    }
+
+    /// Sets how the executor reacts to polls that block it for too long.
+    pub fn set_long_poll_policy(&mut self, policy: LongPollPolicy) {
+        self.spawner.set_long_poll_policy(policy);
+    }
+
+    /// Makes metrics snapshots include a CPU energy estimate.
+    pub fn set_power_model(&mut self, model: impl PowerModel + 'static) {
+        self.spawner.set_power_model(model);
+    }
+
+    /// Metrics for this runtime; the handle stays usable after `block_on`.
+    pub fn metrics(&self) -> MetricsHandle {
+        self.spawner.metrics()
+    }
+
+    pub fn spawner(&self) -> Spawner {
+        self.spawner.clone()
+    }
+
+    /// Spawns a task by name. Unlike other tasks, it is re-spawned by
+    /// `reboot` as `policy` says; `task` is called for each start.
+    pub fn spawn_registered<F, Fut>(
+        &mut self,
+        name: &str,
+        policy: RestartPolicy,
+        task: F,
+    ) -> task::JoinHandle<()>
+    where
+        F: Fn(TaskContext) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let factory = Arc::new(move |context| task(context).boxed());
+        self.registry.register(&self.spawner, name, policy, factory)
+    }
+
+    /// Simulates a power failure followed by a boot: forgets queued wakes,
+    /// drops every in-flight future (their joiners see
+    /// `JoinError::Cancelled`), then re-spawns registered tasks according to
+    /// their policies. Tasks spawned from other threads once the futures are
+    /// being dropped belong to the new boot and are kept.
+    pub fn reboot(&mut self) {
+        // Drained before cancelling, so a task spawned or woken meanwhile is
+        // either cancelled with the rest or still queued, never lost
+        self.executor.ready.clear();
+        while self.executor.receiver.try_recv().is_ok() {}
+        self.spawner.scheduler.cancel_all();
+
+        self.registry.reboot(&self.spawner);
+    }
+
+    /// Number of reboots so far.
+    pub fn boot_count(&self) -> u64 {
+        self.registry.record_boot()
+    }
+
+    // Runs tasks until `root` has finished
+    fn drive<T>(&mut self, root: &task::JoinHandle<T>) {
+        self.executor.activity.resume();
+        while !root.is_finished() {
+            let Some(task) = self.executor.next_task() else {
+                break;
+            };
+            poll_task(&task);
+        }
+        self.executor.activity.suspend();
+    }
+
+    /// Runs tasks until `future` completes and returns its output. Other
+    /// tasks are left where they are, to continue on the next call.
+    pub fn run_until<T>(&mut self, future: impl Future<Output = T> + 'static + Send) -> T
+    where
+        T: Send + 'static,
+    {
+        let mut root = self.spawner.spawn(future);
+        self.drive(&root);
+        take_output(&mut root)
+    }
+
+    /// Runs `future` to completion, along with any task it spawns, and
+    /// returns its output.
+    pub fn block_on<T>(mut self, future: impl Future<Output = T> + 'static + Send) -> T
+    where
+        T: Send + 'static,
+    {
+        let mut root = self.spawner.spawn(future);
+        self.drive(&root);
+
+        let Runtime {
+            mut executor,
+            spawner,
+            shutdown_mode,
+            ..
+        } = self;
+        let scheduler = spawner.scheduler.clone();
+        drop(spawner);
+        match shutdown_mode {
+            ShutdownMode::WaitForTasks => executor.run(),
+            ShutdownMode::CancelTasks => {
+                scheduler.cancel_all();
+                scheduler.close();
+            }
+        }
+
+        take_output(&mut root)
+    }
+}
+
+// The output of a root task the runtime has finished driving
+fn take_output<T>(root: &mut task::JoinHandle<T>) -> T {
+    match root.now_or_never() {
+        Some(Ok(output)) => output,
+        Some(Err(JoinError::Panicked(payload))) => panic::resume_unwind(payload),
+        Some(Err(error)) => panic!("root future failed: {}", error),
+        None => unreachable!("root future finished without a result"),
+    }
+}
+
+impl Default for Runtime {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coop::yield_now;
+    use crate::deadline::with_deadline;
+    use crate::sync::oneshot;
+
+    fn edf_runtime() -> Runtime {
+        let mut runtime = Runtime::new();
+        runtime.set_scheduling_policy(SchedulingPolicy::EarliestDeadlineFirst);
+        runtime
+    }
+
+    #[test]
+    fn edf_runs_earliest_deadline_first() {
+        let runtime = edf_runtime();
+        let spawner = runtime.spawner();
+        let order = Arc::new(Mutex::new(Vec::new()));
+
+        let log = order.clone();
+        runtime.block_on(async move {
+            let start = Instant::now();
+            for millis in [30, 10, 20] {
+                let log = log.clone();
+                spawner.spawn_with_deadline(start + Duration::from_millis(millis), async move {
+                    log.lock().unwrap().push(millis);
+                });
+            }
+            let log = log.clone();
+            spawner.spawn(async move { log.lock().unwrap().push(0) });
+        });
+
+        assert_eq!(*order.lock().unwrap(), [10, 20, 30, 0]);
+    }
+
+    #[test]
+    fn edf_sees_deadline_of_suspended_task() {
+        let runtime = edf_runtime();
+        let spawner = runtime.spawner();
+        let order = Arc::new(Mutex::new(Vec::new()));
+
+        let log = order.clone();
+        runtime.block_on(async move {
+            let start = Instant::now();
+            let (sender, receiver) = oneshot::channel();
+
+            let waiter_log = log.clone();
+            spawner.spawn(async move {
+                with_deadline(start + Duration::from_millis(10), receiver)
+                    .await
+                    .unwrap();
+                waiter_log.lock().unwrap().push("waiter");
+            });
+            // Let the waiter suspend with its deadline in place
+            yield_now().await;
+
+            let log = log.clone();
+            spawner.spawn_with_deadline(start + Duration::from_millis(50), async move {
+                log.lock().unwrap().push("other");
+            });
+            sender.send(()).unwrap();
+        });
+
+        assert_eq!(*order.lock().unwrap(), ["waiter", "other"]);
+    }
+}
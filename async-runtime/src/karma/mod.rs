@@ -2,24 +2,41 @@
 
 use std::{
     collections::VecDeque,
-    fmt::Debug,
+    fmt::{self, Debug},
     marker::PhantomData,
+    pin::Pin,
     sync::{Arc, Mutex},
+    task::{Context, Poll},
+    time::Duration,
 };
 
+use futures::Stream;
+
+use crate::sensor::Sensor;
+use crate::{clock, timer};
+
+// How long a replay waits for the peripheral to reach a state before
+// giving up on it
+const STATE_TIMEOUT: Duration = Duration::from_secs(10);
+
 pub trait PeripheralMsg<S> {
     fn required_initial_state(&self) -> S;
     fn resulting_state(&self) -> S;
 }
 
 pub trait Peripheral<S> {
-    type InputMsg: PeripheralMsg<S> + Debug;
-    type OutputMsg: PeripheralMsg<S> + Debug;
+    type InputMsg: PeripheralMsg<S> + Debug + Clone;
+    type OutputMsg: PeripheralMsg<S> + Debug + Clone;
+    // Why the hardware could not take a message
+    type Error: Debug;
 
     fn get_id(&self) -> u64;
 
     fn get_current_state(&self) -> S;
 
+    // Hands a message to the hardware, without recording it
+    fn send_input(&mut self, msg: Self::InputMsg) -> Result<(), Self::Error>;
+
     fn power_cycle(&mut self);
 }
 
@@ -29,13 +46,40 @@ pub enum InputOrOutput<I, O> {
     Output(O),
 }
 
+pub type SupportQueue<I, O> = Arc<Mutex<VecDeque<InputOrOutput<I, O>>>>;
+
+/// Why a replay could not bring the peripheral back.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReplayError<S, E> {
+    /// The peripheral stayed in a state other than the one the support
+    /// queue expects next.
+    Stuck { expected: S, found: S },
+    /// The peripheral could not take a replayed input.
+    Send(E),
+}
+
+impl<S: Debug, E: Debug> fmt::Display for ReplayError<S, E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplayError::Stuck { expected, found } => write!(
+                f,
+                "peripheral stayed in {:?} while waiting for {:?}",
+                found, expected
+            ),
+            ReplayError::Send(error) => write!(f, "peripheral rejected an input: {:?}", error),
+        }
+    }
+}
+
+impl<S: Debug, E: Debug> std::error::Error for ReplayError<S, E> {}
+
 #[derive(Clone)]
 pub struct Karma<P, S>
 where
     P: Peripheral<S>,
 {
     peripheral: P,
-    support_queue: Arc<Mutex<VecDeque<InputOrOutput<P::InputMsg, P::OutputMsg>>>>,
+    support_queue: SupportQueue<P::InputMsg, P::OutputMsg>,
 
     _pd: PhantomData<S>,
 }
@@ -43,6 +87,7 @@ where
 impl<P, S> Karma<P, S>
 where
     P: Peripheral<S>,
+    S: PartialEq + Debug,
 {
     pub fn new(peripheral: P) -> Self {
         Self {
@@ -53,12 +98,145 @@ where
         }
     }
 
-    pub async fn replay_support_queue(&mut self) {
-        for e in self.support_queue.lock().unwrap().iter() {
-            // TODO
+    /// The managed peripheral, e.g. to take the outputs it raises.
+    pub fn peripheral(&self) -> &P {
+        &self.peripheral
+    }
+
+    pub fn push_to_support_queue(&self, event: InputOrOutput<P::InputMsg, P::OutputMsg>) {
+        // Ignore any inputs or outputs that don't affect the state machine
+        let (from, to) = match &event {
+            InputOrOutput::Input(input) => {
+                (input.required_initial_state(), input.resulting_state())
+            }
+            InputOrOutput::Output(output) => {
+                (output.required_initial_state(), output.resulting_state())
+            }
+        };
+        if from != to {
+            self.support_queue.lock().unwrap().push_back(event);
+        }
+    }
+
+    /// Sends `msg` to the peripheral and records it for replay, unless the
+    /// peripheral fails to take it. For peripherals that handle commands
+    /// without replying.
+    pub fn send(&mut self, msg: P::InputMsg) -> Result<(), P::Error> {
+        let state = self.peripheral.get_current_state();
+        assert!(
+            state == msg.required_initial_state(),
+            "peripheral {} cannot handle {:?} in state {:?}",
+            self.peripheral.get_id(),
+            msg,
+            state
+        );
+
+        self.peripheral.send_input(msg.clone())?;
+        self.push_to_support_queue(InputOrOutput::Input(msg));
+        Ok(())
+    }
+
+    /// Cuts and restores the peripheral's power. Only what was recorded
+    /// since it was last brought up from the state it resets to is kept
+    /// for replay; anything earlier was undone by a power cycle.
+    pub fn power_cycle(&mut self) {
+        self.peripheral.power_cycle();
+
+        let reset = self.peripheral.get_current_state();
+        let mut queue = self.support_queue.lock().unwrap();
+        let last_bring_up = queue.iter().rposition(|event| {
+            matches!(event, InputOrOutput::Input(input) if input.required_initial_state() == reset)
+        });
+        if let Some(index) = last_bring_up {
+            queue.drain(..index);
+        }
+    }
+
+    /// Brings the peripheral back to the state recorded in the support
+    /// queue, e.g. after a power cycle, by sending the recorded inputs
+    /// again in order. Fails if the peripheral does not reach a state the
+    /// queue expects in time, or rejects one of the inputs.
+    pub async fn replay_support_queue(&mut self) -> Result<(), ReplayError<S, P::Error>> {
+        let events: Vec<_> = self.support_queue.lock().unwrap().iter().cloned().collect();
+        let Some(last) = events.last() else {
+            return Ok(());
+        };
+        let target = match last {
+            InputOrOutput::Input(input) => input.resulting_state(),
+            InputOrOutput::Output(output) => output.resulting_state(),
+        };
+        // Peripherals that kept their state through the power cycle
+        if self.peripheral.get_current_state() == target {
+            return Ok(());
+        }
+
+        for event in events {
+            // Each input waits for the state it was originally sent in,
+            // and each output for the hardware to get where it led
+            let (state, input) = match event {
+                InputOrOutput::Input(input) => (input.required_initial_state(), Some(input)),
+                InputOrOutput::Output(output) => (output.resulting_state(), None),
+            };
+            self.wait_for_state(state).await?;
+            if let Some(input) = input {
+                self.peripheral
+                    .send_input(input)
+                    .map_err(ReplayError::Send)?;
+            }
         }
+        self.wait_for_state(target).await
+    }
+
+    // Peripherals cannot signal state changes, so this polls
+    async fn wait_for_state(&self, state: S) -> Result<(), ReplayError<S, P::Error>> {
+        let deadline = clock::now() + STATE_TIMEOUT;
+        loop {
+            let found = self.peripheral.get_current_state();
+            if found == state {
+                return Ok(());
+            }
+            if clock::now() >= deadline {
+                return Err(ReplayError::Stuck {
+                    expected: state,
+                    found,
+                });
+            }
+            timer::sleep(Duration::from_millis(1)).await;
+        }
+    }
+}
+
+// A Karma-managed sensor is read like the sensor itself
+impl<P, S> Stream for Karma<P, S>
+where
+    P: Peripheral<S> + Sensor,
+    S: Unpin,
+{
+    type Item = P::Item;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<P::Item>> {
+        Pin::new(&mut self.peripheral).poll_next(cx)
+    }
+}
+
+impl<P, S> Sensor for Karma<P, S>
+where
+    P: Peripheral<S> + Sensor,
+    S: Unpin,
+{
+    fn units(&self) -> &'static str {
+        self.peripheral.units()
+    }
+
+    fn sampling_period(&self) -> Duration {
+        self.peripheral.sampling_period()
+    }
+
+    fn poll_read(&mut self, cx: &mut Context<'_>, max: usize) -> Poll<Vec<P::Item>> {
+        self.peripheral.poll_read(cx, max)
     }
 }
 
 // Simulated peripherals
 pub mod radio;
+pub mod rtc;
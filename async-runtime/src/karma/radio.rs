@@ -1,9 +1,10 @@
+use crate::coop;
 use crate::karma::{InputOrOutput, Karma, Peripheral, PeripheralMsg};
 
 use crossbeam::channel::{Receiver, Sender, select, unbounded};
 use rand::{Rng, SeedableRng, rngs::SmallRng};
 use std::{
-    collections::VecDeque,
+    convert::Infallible,
     pin::Pin,
     sync::{Arc, Mutex},
     task::{Context, Poll, Waker},
@@ -81,8 +82,9 @@ pub struct Radio {
     // "Interrupt" sender
     wakers: Arc<Mutex<Vec<Waker>>>,
 
-    // Used for power cycles
+    // Used for power cycles, acknowledged once the hardware has reset
     power_cycle_sender: Sender<()>,
+    power_cycle_ack: Receiver<()>,
 
     // Queue for sending commands from CPU to radio
     command_sender: Sender<RadioInputMsg>,
@@ -93,6 +95,7 @@ pub struct Radio {
 impl Peripheral<RadioState> for Radio {
     type InputMsg = RadioInputMsg;
     type OutputMsg = RadioOutputMsg;
+    type Error = Infallible;
 
     fn get_id(&self) -> u64 {
         self.id
@@ -102,16 +105,24 @@ impl Peripheral<RadioState> for Radio {
         *self.current_state.lock().unwrap()
     }
 
+    fn send_input(&mut self, msg: RadioInputMsg) -> Result<(), Infallible> {
+        self.command_sender.send(msg).unwrap();
+        Ok(())
+    }
+
     fn power_cycle(&mut self) {
-        // Send a power cycle signal to the hw
+        // Send a power cycle signal to the hw, and wait for it to take
+        // effect so that later commands find the radio reset
         self.power_cycle_sender.send(()).unwrap();
+        self.power_cycle_ack.recv().unwrap();
     }
 }
 
 pub struct RadioFuture {
     wakers: Arc<Mutex<Vec<Waker>>>,
     receiver: Receiver<RadioOutputMsg>,
-    support_queue: Arc<Mutex<VecDeque<InputOrOutput<RadioInputMsg, RadioOutputMsg>>>>,
+    // Shares the support queue of the Karma the future was created from
+    karma: Karma<Radio, RadioState>,
 
     orig_arg: RadioFutureCreateArg,
 }
@@ -120,35 +131,40 @@ impl Future for RadioFuture {
     type Output = Option<RadioOutputMsg>;
 
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        match self.orig_arg {
-            RadioFutureCreateArg::InputMsg(
+        coop::cooperative(cx, |cx| {
+            if let RadioFutureCreateArg::InputMsg(
                 RadioInputMsg::StateTransmit | RadioInputMsg::StateReceive,
-            ) => return Poll::Ready(None),
-            _ => (),
-        }
+            ) = self.orig_arg
+            {
+                return Poll::Ready(None);
+            }
 
-        // Try to receive the message
-        while let Ok(msg) = self.receiver.try_recv() {
-            // If we see a message that matches what we're waiting for,
-            // return Ready
-            match (&self.orig_arg, &msg) {
-                (RadioFutureCreateArg::InputMsg(RadioInputMsg::Init), RadioOutputMsg::InitDone)
-                | (
-                    RadioFutureCreateArg::InputMsg(RadioInputMsg::Send(_)),
-                    RadioOutputMsg::SendDone,
-                )
-                | (RadioFutureCreateArg::AwaitReceive, RadioOutputMsg::DataReceived(_)) => {
-                    self.push_to_support_queue(InputOrOutput::Output(msg.clone()));
-                    return Poll::Ready(Some(msg));
+            // Try to receive the message
+            while let Ok(msg) = self.receiver.try_recv() {
+                // If we see a message that matches what we're waiting for,
+                // return Ready
+                match (&self.orig_arg, &msg) {
+                    (
+                        RadioFutureCreateArg::InputMsg(RadioInputMsg::Init),
+                        RadioOutputMsg::InitDone,
+                    )
+                    | (
+                        RadioFutureCreateArg::InputMsg(RadioInputMsg::Send(_)),
+                        RadioOutputMsg::SendDone,
+                    )
+                    | (RadioFutureCreateArg::AwaitReceive, RadioOutputMsg::DataReceived(_)) => {
+                        self.push_to_support_queue(InputOrOutput::Output(msg.clone()));
+                        return Poll::Ready(Some(msg));
+                    }
+                    _ => (),
                 }
-                _ => (),
             }
-        }
 
-        // If we didn't see anything right now, set waker and remain pending
-        let mut wakers = self.wakers.lock().unwrap();
-        wakers.push(cx.waker().clone());
-        Poll::Pending
+            // If we didn't see anything right now, set waker and remain pending
+            let mut wakers = self.wakers.lock().unwrap();
+            wakers.push(cx.waker().clone());
+            Poll::Pending
+        })
     }
 }
 
@@ -160,38 +176,22 @@ pub enum RadioFutureCreateArg {
 
 impl RadioFuture {
     pub fn push_to_support_queue(&mut self, event: InputOrOutput<RadioInputMsg, RadioOutputMsg>) {
-        // Ignore any inputs or outputs that don't affect the state machine
-        let has_sm_effect = match &event {
-            InputOrOutput::Input(input)
-                if input.required_initial_state() == input.resulting_state() =>
-            {
-                false
-            }
-            InputOrOutput::Output(output)
-                if output.required_initial_state() == output.resulting_state() =>
-            {
-                false
-            }
-            _ => true,
-        };
-        if has_sm_effect {
-            self.support_queue.lock().unwrap().push_back(event);
-        }
+        self.karma.push_to_support_queue(event);
 
         println!(
             "Current support queue: {:?}",
-            *self.support_queue.lock().unwrap()
+            *self.karma.support_queue.lock().unwrap()
         );
     }
 
     pub fn new(karma: &mut Karma<Radio, RadioState>, arg: RadioFutureCreateArg) -> Self {
-        let radio = &mut karma.peripheral;
+        let radio = &karma.peripheral;
 
         let mut ret = Self {
             wakers: radio.wakers.clone(),
             receiver: radio.interrupt_receiver.clone(),
             orig_arg: arg.clone(),
-            support_queue: karma.support_queue.clone(),
+            karma: karma.clone(),
         };
 
         match arg {
@@ -220,6 +220,7 @@ impl Radio {
         let (data_gen_sender, data_gen_receiver) = unbounded();
 
         let (power_cycle_sender, power_cycle_receiver) = unbounded();
+        let (power_cycle_ack_sender, power_cycle_ack) = unbounded();
 
         let wakers = Arc::new(Mutex::new(vec![]));
 
@@ -234,6 +235,7 @@ impl Radio {
                 interrupt_sender,
                 data_gen_receiver,
                 power_cycle_receiver,
+                power_cycle_ack_sender,
             );
         });
 
@@ -249,6 +251,7 @@ impl Radio {
             interrupt_receiver,
             wakers,
             power_cycle_sender,
+            power_cycle_ack,
         }
     }
 }
@@ -261,6 +264,7 @@ fn radio_backend(
     interrupt_sender: Sender<RadioOutputMsg>,
     data_gen_receiver: Receiver<Vec<u8>>,
     power_cycle_receiver: Receiver<()>,
+    power_cycle_ack_sender: Sender<()>,
 ) {
     loop {
         let prev_state = *state.lock().unwrap();
@@ -276,6 +280,7 @@ fn radio_backend(
                 println!("Radio received power-cycle signal; resetting");
 
                 *state.lock().unwrap() = RadioState::NotInit;
+                power_cycle_ack_sender.send(()).unwrap();
             }
             // Receive some data over the radio
             recv(data_gen_receiver) -> data => {
@@ -0,0 +1,181 @@
+use crate::clock::WallClock;
+use crate::karma::{Peripheral, PeripheralMsg};
+
+use serde::{Deserialize, Serialize};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RtcState {
+    Stopped,
+    Running,
+}
+
+#[derive(Clone, Debug)]
+pub enum RtcInputMsg {
+    Start,
+    Stop,
+    // Time since the Unix epoch
+    SetTime(Duration),
+}
+
+// The RTC raises no interrupts
+#[derive(Clone, Debug)]
+pub enum RtcOutputMsg {}
+
+impl PeripheralMsg<RtcState> for RtcInputMsg {
+    fn required_initial_state(&self) -> RtcState {
+        match self {
+            RtcInputMsg::Start => RtcState::Stopped,
+            RtcInputMsg::Stop => RtcState::Running,
+            RtcInputMsg::SetTime(_) => RtcState::Running,
+        }
+    }
+
+    fn resulting_state(&self) -> RtcState {
+        match self {
+            RtcInputMsg::Start => RtcState::Running,
+            RtcInputMsg::Stop => RtcState::Stopped,
+            RtcInputMsg::SetTime(_) => RtcState::Running,
+        }
+    }
+}
+
+impl PeripheralMsg<RtcState> for RtcOutputMsg {
+    fn required_initial_state(&self) -> RtcState {
+        match *self {}
+    }
+
+    fn resulting_state(&self) -> RtcState {
+        match *self {}
+    }
+}
+
+// The RTC "hardware" registers, kept in a file so they outlive the process
+// the way a battery-backed RTC outlives the CPU's power
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+struct Registers {
+    state: RtcState,
+    // While running: host time at which the counter read zero. While
+    // stopped: the counter value it stopped at.
+    counter: Duration,
+}
+
+/// A simulated battery-backed real-time clock. It keeps counting while the
+/// CPU is off (including while no process is running), and can be
+/// installed as the process's wall clock with `clock::set_wall_clock`.
+#[derive(Clone)]
+pub struct Rtc {
+    id: u64,
+    path: PathBuf,
+    registers: Arc<Mutex<Registers>>,
+}
+
+impl Peripheral<RtcState> for Rtc {
+    type InputMsg = RtcInputMsg;
+    type OutputMsg = RtcOutputMsg;
+    type Error = io::Error;
+
+    fn get_id(&self) -> u64 {
+        self.id
+    }
+
+    fn get_current_state(&self) -> RtcState {
+        self.registers.lock().unwrap().state
+    }
+
+    // Commands take effect immediately and are persisted before this
+    // returns
+    fn send_input(&mut self, msg: RtcInputMsg) -> io::Result<()> {
+        self.apply(&msg)
+    }
+
+    fn power_cycle(&mut self) {
+        // The counter has its own supply; only the CPU's view is reloaded
+        if let Ok(registers) = Registers::load(&self.path) {
+            *self.registers.lock().unwrap() = registers;
+        }
+    }
+}
+
+fn host_time() -> Duration {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap()
+}
+
+impl Registers {
+    fn load(path: &Path) -> io::Result<Self> {
+        let json = fs::read_to_string(path)?;
+        serde_json::from_str(&json).map_err(io::Error::other)
+    }
+
+    fn store(&self, path: &Path) -> io::Result<()> {
+        // Written aside and renamed, so a crash never leaves half a file
+        let temp = path.with_extension("tmp");
+        fs::write(&temp, serde_json::to_string(self)?)?;
+        fs::rename(temp, path)
+    }
+
+    // Registers in `state` whose counter currently reads `reading`
+    fn holding(state: RtcState, reading: Duration) -> Self {
+        let counter = match state {
+            RtcState::Running => host_time().saturating_sub(reading),
+            RtcState::Stopped => reading,
+        };
+        Registers { state, counter }
+    }
+
+    fn read(&self) -> Duration {
+        match self.state {
+            RtcState::Running => host_time().saturating_sub(self.counter),
+            RtcState::Stopped => self.counter,
+        }
+    }
+}
+
+impl Rtc {
+    /// Opens the RTC whose registers live at `path`, creating a stopped
+    /// one if the file does not exist yet.
+    pub fn open(id: u64, path: impl Into<PathBuf>) -> io::Result<Self> {
+        let path = path.into();
+        let registers = match Registers::load(&path) {
+            Ok(registers) => registers,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                let registers = Registers::holding(RtcState::Stopped, Duration::ZERO);
+                registers.store(&path)?;
+                registers
+            }
+            Err(e) => return Err(e),
+        };
+
+        Ok(Rtc {
+            id,
+            path,
+            registers: Arc::new(Mutex::new(registers)),
+        })
+    }
+
+    /// The counter: time since the Unix epoch, once set.
+    pub fn now(&self) -> Duration {
+        self.registers.lock().unwrap().read()
+    }
+
+    fn apply(&self, msg: &RtcInputMsg) -> io::Result<()> {
+        let mut registers = self.registers.lock().unwrap();
+        let reading = match msg {
+            RtcInputMsg::SetTime(time) => *time,
+            _ => registers.read(),
+        };
+        *registers = Registers::holding(msg.resulting_state(), reading);
+        registers.store(&self.path)
+    }
+}
+
+impl WallClock for Rtc {
+    fn timestamp(&self) -> Duration {
+        self.now()
+    }
+}
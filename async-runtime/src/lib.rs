@@ -1,5 +1,18 @@
+pub mod blocking;
+pub mod clock;
+pub mod coop;
+pub mod deadline;
 pub mod executor;
 pub mod karma;
+pub mod local_executor;
+pub mod metrics;
+pub mod power;
+pub mod registry;
+pub mod scope;
+pub mod sensor;
+pub mod sync;
 pub mod task;
+pub mod task_local;
 pub mod temperature_sensor;
 pub mod timer;
+pub mod work_stealing;
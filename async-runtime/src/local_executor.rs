@@ -0,0 +1,288 @@
+use std::{
+    cell::RefCell,
+    collections::{HashMap, VecDeque},
+    panic::{self, AssertUnwindSafe},
+    rc::Rc,
+    sync::{
+        Arc, Condvar, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    task::{Context, Poll},
+};
+
+use futures::{
+    FutureExt,
+    future::LocalBoxFuture,
+    task::{ArcWake, waker},
+};
+
+use crate::coop;
+use crate::task::{
+    Abortable, JoinError, JoinHandle, PanicHook, PanicPayload, PanicSink, TaskId,
+    default_panic_hook, join_pair,
+};
+
+// Ids of tasks ready to run. Shared with wakers, which may be called from
+// any thread (timer threads, peripheral backends), so it must be `Sync` even
+// though the tasks themselves never leave the executor's thread.
+struct ReadyQueue {
+    ids: Mutex<VecDeque<TaskId>>,
+    available: Condvar,
+}
+
+impl ReadyQueue {
+    fn push(&self, id: TaskId) {
+        self.ids.lock().unwrap().push_back(id);
+        self.available.notify_one();
+    }
+
+    fn pop(&self) -> TaskId {
+        let mut ids = self.ids.lock().unwrap();
+        loop {
+            if let Some(id) = ids.pop_front() {
+                return id;
+            }
+            ids = self.available.wait(ids).unwrap();
+        }
+    }
+}
+
+// The thread-safe part of a local task: what wakers and abort handles see
+struct LocalWaker {
+    id: TaskId,
+    // Set while the task's id is in the ready queue, so wakes coalesce
+    scheduled: AtomicBool,
+    // Set by `AbortHandle::abort`; the executor drops the future instead of polling it
+    aborted: AtomicBool,
+    // Set once the task's future is gone
+    finished: AtomicBool,
+    ready: Arc<ReadyQueue>,
+}
+
+impl ArcWake for LocalWaker {
+    fn wake_by_ref(arc_self: &Arc<Self>) {
+        if !arc_self.scheduled.swap(true, Ordering::AcqRel) {
+            arc_self.ready.push(arc_self.id);
+        }
+    }
+}
+
+impl Abortable for LocalWaker {
+    fn abort(self: Arc<Self>) {
+        self.aborted.store(true, Ordering::Release);
+        // Get the task to its next scheduling point
+        ArcWake::wake_by_ref(&self);
+    }
+
+    fn is_finished(&self) -> bool {
+        self.finished.load(Ordering::Acquire)
+    }
+}
+
+struct LocalTask {
+    future: LocalBoxFuture<'static, ()>,
+    waker: Arc<LocalWaker>,
+
+    join_sink: Arc<dyn PanicSink>,
+}
+
+impl Drop for LocalTask {
+    fn drop(&mut self) {
+        self.waker.finished.store(true, Ordering::Release);
+    }
+}
+
+struct LocalShared {
+    // Tasks not currently being polled
+    tasks: RefCell<HashMap<TaskId, LocalTask>>,
+    ready: Arc<ReadyQueue>,
+    panic_hook: RefCell<PanicHook>,
+}
+
+/// An executor for futures that are not `Send`. Tasks run on the thread
+/// that drives the executor (through `run` or `block_on`), so they can hold
+/// `Rc`s and other thread-bound state.
+///
+/// Local tasks are polled by their own loop rather than the one behind
+/// `Runtime`, and so miss some of what `Spawner` tasks get:
+///
+/// - no task is current while they are polled, so `task_local!` keys,
+///   `current_task_id` and `with_deadline` have nothing to act on;
+/// - their polls and wakes are not recorded in any metrics;
+/// - no long-poll policy is applied, so blocking polls go unreported.
+///
+/// Tasks still pending when the executor is dropped are dropped with it,
+/// which also breaks cycles through spawners they hold.
+pub struct LocalExecutor {
+    shared: Rc<LocalShared>,
+}
+
+/// Spawns tasks onto a `LocalExecutor`. Neither is `Send`.
+#[derive(Clone)]
+pub struct LocalSpawner {
+    shared: Rc<LocalShared>,
+}
+
+impl LocalSpawner {
+    pub fn spawn<T: 'static>(&self, future: impl Future<Output = T> + 'static) -> JoinHandle<T> {
+        let id = TaskId::next();
+        let (notifier, mut handle) = join_pair(id);
+        let future = async move {
+            let output = future.await;
+            notifier.complete(Ok(output));
+        }
+        .boxed_local();
+        let waker = Arc::new(LocalWaker {
+            id,
+            scheduled: AtomicBool::new(true),
+            aborted: AtomicBool::new(false),
+            finished: AtomicBool::new(false),
+            ready: self.shared.ready.clone(),
+        });
+        handle.task = Arc::<LocalWaker>::downgrade(&waker);
+
+        self.shared.tasks.borrow_mut().insert(
+            id,
+            LocalTask {
+                future,
+                waker,
+                join_sink: handle.shared_state.clone(),
+            },
+        );
+        self.shared.ready.push(id);
+
+        handle
+    }
+
+    /// Number of spawned tasks that have not finished yet.
+    pub fn live_tasks(&self) -> usize {
+        self.shared.tasks.borrow().len()
+    }
+}
+
+impl LocalExecutor {
+    pub fn new() -> Self {
+        LocalExecutor {
+            shared: Rc::new(LocalShared {
+                tasks: RefCell::new(HashMap::new()),
+                ready: Arc::new(ReadyQueue {
+                    ids: Mutex::new(VecDeque::new()),
+                    available: Condvar::new(),
+                }),
+                panic_hook: RefCell::new(Arc::new(default_panic_hook)),
+            }),
+        }
+    }
+
+    /// Replaces the hook called whenever a task panics. The default one
+    /// logs the task and panic message to stderr.
+    pub fn set_panic_hook(&mut self, hook: impl Fn(TaskId, &PanicPayload) + Send + Sync + 'static) {
+        *self.shared.panic_hook.borrow_mut() = Arc::new(hook);
+    }
+
+    pub fn spawner(&self) -> LocalSpawner {
+        LocalSpawner {
+            shared: self.shared.clone(),
+        }
+    }
+
+    // Polls the task once, if it still exists
+    fn poll_task(&self, id: TaskId) {
+        // Taken out of the map while polling, so the task can spawn others
+        let Some(mut task) = self.shared.tasks.borrow_mut().remove(&id) else {
+            return;
+        };
+        // Clear before polling, so a wake during the poll requeues the task
+        task.waker.scheduled.store(false, Ordering::Release);
+
+        if task.waker.aborted.load(Ordering::Acquire) {
+            // Dropping the task runs the future's destructors; its join
+            // handle reports the task as cancelled
+            return;
+        }
+
+        let _budget = coop::budget();
+        let waker = waker(task.waker.clone());
+        let mut context = Context::from_waker(&waker);
+
+        // call poll, isolating the other tasks from a panic
+        let result =
+            panic::catch_unwind(AssertUnwindSafe(|| task.future.as_mut().poll(&mut context)));
+        match result {
+            Ok(Poll::Ready(())) => (),
+            Ok(Poll::Pending) => {
+                self.shared.tasks.borrow_mut().insert(id, task);
+            }
+            Err(payload) => {
+                let hook = self.shared.panic_hook.borrow().clone();
+                hook(id, &payload);
+                task.join_sink.panicked(payload);
+            }
+        }
+    }
+
+    /// Runs tasks until none is left.
+    pub fn run(&self) {
+        while !self.shared.tasks.borrow().is_empty() {
+            let id = self.shared.ready.pop();
+            self.poll_task(id);
+        }
+    }
+
+    /// Runs `future` to completion on the current thread, along with any
+    /// task it spawns, and returns its output. Tasks still pending once it
+    /// completes are left for a later `run` or `block_on`.
+    pub fn block_on<T: 'static>(&self, future: impl Future<Output = T> + 'static) -> T {
+        let mut root = self.spawner().spawn(future);
+
+        while !root.is_finished() {
+            let id = self.shared.ready.pop();
+            self.poll_task(id);
+        }
+
+        match (&mut root).now_or_never() {
+            Some(Ok(output)) => output,
+            Some(Err(JoinError::Panicked(payload))) => panic::resume_unwind(payload),
+            Some(Err(error)) => panic!("root future failed: {}", error),
+            None => unreachable!("root future finished without a result"),
+        }
+    }
+}
+
+impl Drop for LocalExecutor {
+    fn drop(&mut self) {
+        // Taken out first, as the futures' destructors may spawn more tasks
+        let tasks = std::mem::take(&mut *self.shared.tasks.borrow_mut());
+        drop(tasks);
+    }
+}
+
+impl Default for LocalExecutor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn drop_releases_pending_tasks() {
+        let executor = LocalExecutor::new();
+        let spawner = executor.spawner();
+        let marker = Rc::new(());
+
+        // Holds its own spawner, which would keep the task alive in a cycle
+        let held = (spawner.clone(), marker.clone());
+        spawner.spawn(async move {
+            let _held = held;
+            futures::future::pending::<()>().await;
+        });
+        executor.block_on(async {});
+
+        drop(executor);
+        assert_eq!(Rc::strong_count(&marker), 1);
+        assert_eq!(spawner.live_tasks(), 0);
+    }
+}
@@ -0,0 +1,305 @@
+use std::{
+    collections::{BTreeMap, VecDeque},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use serde::Serialize;
+
+use crate::{
+    deadline::DeadlineStats,
+    power::{EnergyEstimate, PowerModel},
+    task::TaskId,
+};
+
+/// How a task ended, or that it hasn't yet.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub enum TaskStatus {
+    Running,
+    Completed,
+    Cancelled,
+    Panicked,
+}
+
+/// What the executor recorded about one task. Times are relative to when
+/// the executor was created.
+#[derive(Clone, Debug, Serialize)]
+pub struct TaskMetrics {
+    pub id: TaskId,
+    pub spawned_at: Duration,
+    pub finished_at: Option<Duration>,
+    pub status: TaskStatus,
+
+    pub poll_count: u64,
+    pub total_poll_time: Duration,
+    pub max_poll_time: Duration,
+    /// Polls that exceeded the executor's long-poll threshold
+    pub long_polls: u64,
+    pub wake_count: u64,
+}
+
+/// Totals over finished tasks that are no longer listed one by one.
+#[derive(Clone, Copy, Debug, Default, Serialize)]
+pub struct RetiredTasks {
+    pub count: u64,
+    pub completed: u64,
+    pub cancelled: u64,
+    pub panicked: u64,
+    pub poll_count: u64,
+    pub total_poll_time: Duration,
+    pub long_polls: u64,
+    pub wake_count: u64,
+}
+
+impl RetiredTasks {
+    fn add(&mut self, task: &TaskMetrics) {
+        self.count += 1;
+        match task.status {
+            TaskStatus::Completed => self.completed += 1,
+            TaskStatus::Cancelled => self.cancelled += 1,
+            TaskStatus::Panicked => self.panicked += 1,
+            TaskStatus::Running => (),
+        }
+        self.poll_count += task.poll_count;
+        self.total_poll_time += task.total_poll_time;
+        self.long_polls += task.long_polls;
+        self.wake_count += task.wake_count;
+    }
+}
+
+/// A point-in-time copy of everything an executor recorded.
+#[derive(Clone, Debug, Serialize)]
+pub struct MetricsSnapshot {
+    pub uptime: Duration,
+    /// Time the executor spent running tasks, summed over its threads
+    pub active_time: Duration,
+    /// Time the executor spent blocked waiting for a ready task
+    pub idle_time: Duration,
+    /// Transitions from idle back to active
+    pub wakeups: u64,
+    /// Set when the executor was given a power model
+    pub energy: Option<EnergyEstimate>,
+    pub poll_count: u64,
+    pub long_polls: u64,
+    /// Wakes dropped because the run queue was full
+    pub dropped_wakes: u64,
+    pub deadlines: DeadlineStats,
+    /// Live tasks, and the most recently finished ones
+    pub tasks: Vec<TaskMetrics>,
+    /// Older finished tasks, folded together to bound memory
+    pub retired: RetiredTasks,
+}
+
+impl MetricsSnapshot {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("metrics are always serializable")
+    }
+
+    pub fn task(&self, id: TaskId) -> Option<&TaskMetrics> {
+        self.tasks.iter().find(|task| task.id == id)
+    }
+
+    pub fn estimate_energy(&self, model: &dyn PowerModel) -> EnergyEstimate {
+        model.estimate(self.active_time, self.idle_time, self.wakeups)
+    }
+}
+
+// How many finished tasks keep their own entry; older ones are retired
+const FINISHED_TASKS_KEPT: usize = 1024;
+
+/// Per-task counters bumped on every poll and wake, updated without taking
+/// the metrics lock.
+#[derive(Default)]
+pub(crate) struct TaskCounters {
+    poll_count: AtomicU64,
+    // In nanoseconds
+    total_poll_time: AtomicU64,
+    max_poll_time: AtomicU64,
+    long_polls: AtomicU64,
+    wake_count: AtomicU64,
+}
+
+impl TaskCounters {
+    pub(crate) fn polled(&self, duration: Duration) {
+        let nanos = duration.as_nanos().try_into().unwrap_or(u64::MAX);
+        self.poll_count.fetch_add(1, Ordering::Relaxed);
+        self.total_poll_time.fetch_add(nanos, Ordering::Relaxed);
+        self.max_poll_time.fetch_max(nanos, Ordering::Relaxed);
+    }
+
+    pub(crate) fn long_poll(&self) {
+        self.long_polls.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn woken(&self) {
+        self.wake_count.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+struct TaskRecord {
+    spawned_at: Duration,
+    finished_at: Option<Duration>,
+    status: TaskStatus,
+    counters: Arc<TaskCounters>,
+}
+
+impl TaskRecord {
+    fn metrics(&self, id: TaskId) -> TaskMetrics {
+        let counters = &*self.counters;
+        TaskMetrics {
+            id,
+            spawned_at: self.spawned_at,
+            finished_at: self.finished_at,
+            status: self.status,
+            poll_count: counters.poll_count.load(Ordering::Relaxed),
+            total_poll_time: Duration::from_nanos(counters.total_poll_time.load(Ordering::Relaxed)),
+            max_poll_time: Duration::from_nanos(counters.max_poll_time.load(Ordering::Relaxed)),
+            long_polls: counters.long_polls.load(Ordering::Relaxed),
+            wake_count: counters.wake_count.load(Ordering::Relaxed),
+        }
+    }
+}
+
+struct MetricsInner {
+    tasks: BTreeMap<TaskId, TaskRecord>,
+    // Finished tasks still in `tasks`, oldest first
+    finished: VecDeque<TaskId>,
+    retired: RetiredTasks,
+    active_time: Duration,
+    idle_time: Duration,
+    wakeups: u64,
+    power_model: Option<Arc<dyn PowerModel>>,
+    deadlines: DeadlineStats,
+}
+
+/// Metrics store shared by an executor and its tasks.
+pub(crate) struct Metrics {
+    started_at: Instant,
+    dropped_wakes: AtomicU64,
+    inner: Mutex<MetricsInner>,
+}
+
+impl Metrics {
+    pub(crate) fn new() -> Arc<Self> {
+        Arc::new(Metrics {
+            started_at: Instant::now(),
+            dropped_wakes: AtomicU64::new(0),
+            inner: Mutex::new(MetricsInner {
+                tasks: BTreeMap::new(),
+                finished: VecDeque::new(),
+                retired: RetiredTasks::default(),
+                active_time: Duration::ZERO,
+                idle_time: Duration::ZERO,
+                wakeups: 0,
+                power_model: None,
+                deadlines: DeadlineStats::default(),
+            }),
+        })
+    }
+
+    pub(crate) fn task_spawned(&self, id: TaskId, counters: Arc<TaskCounters>) {
+        let spawned_at = self.started_at.elapsed();
+        self.inner.lock().unwrap().tasks.insert(
+            id,
+            TaskRecord {
+                spawned_at,
+                finished_at: None,
+                status: TaskStatus::Running,
+                counters,
+            },
+        );
+    }
+
+    pub(crate) fn task_finished(&self, id: TaskId, status: TaskStatus) {
+        let finished_at = self.started_at.elapsed();
+        let mut inner = self.inner.lock().unwrap();
+        let Some(task) = inner.tasks.get_mut(&id) else {
+            return;
+        };
+        task.status = status;
+        task.finished_at = Some(finished_at);
+
+        inner.finished.push_back(id);
+        while inner.finished.len() > FINISHED_TASKS_KEPT {
+            let oldest = inner.finished.pop_front().unwrap();
+            if let Some(task) = inner.tasks.remove(&oldest) {
+                inner.retired.add(&task.metrics(oldest));
+            }
+        }
+    }
+
+    pub(crate) fn wake_dropped(&self) {
+        self.dropped_wakes.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_deadline(&self, deadline: Instant, now: Instant) {
+        self.inner.lock().unwrap().deadlines.record(deadline, now);
+    }
+
+    pub(crate) fn record_active(&self, duration: Duration) {
+        self.inner.lock().unwrap().active_time += duration;
+    }
+
+    // Each idle period ends with a wake-up
+    pub(crate) fn record_idle(&self, duration: Duration) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.idle_time += duration;
+        inner.wakeups += 1;
+    }
+
+    pub(crate) fn set_power_model(&self, model: Arc<dyn PowerModel>) {
+        self.inner.lock().unwrap().power_model = Some(model);
+    }
+
+    pub(crate) fn snapshot(&self) -> MetricsSnapshot {
+        let inner = self.inner.lock().unwrap();
+        let tasks: Vec<_> = inner
+            .tasks
+            .iter()
+            .map(|(id, task)| task.metrics(*id))
+            .collect();
+        MetricsSnapshot {
+            uptime: self.started_at.elapsed(),
+            active_time: inner.active_time,
+            idle_time: inner.idle_time,
+            wakeups: inner.wakeups,
+            energy: inner
+                .power_model
+                .as_ref()
+                .map(|model| model.estimate(inner.active_time, inner.idle_time, inner.wakeups)),
+            poll_count: inner.retired.poll_count
+                + tasks.iter().map(|task| task.poll_count).sum::<u64>(),
+            long_polls: inner.retired.long_polls
+                + tasks.iter().map(|task| task.long_polls).sum::<u64>(),
+            dropped_wakes: self.dropped_wakes.load(Ordering::Relaxed),
+            deadlines: inner.deadlines,
+            tasks,
+            retired: inner.retired,
+        }
+    }
+}
+
+/// Read access to an executor's metrics. Holding one does not keep the
+/// executor alive.
+#[derive(Clone)]
+pub struct MetricsHandle {
+    pub(crate) metrics: Arc<Metrics>,
+}
+
+impl MetricsHandle {
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        self.metrics.snapshot()
+    }
+
+    pub fn task(&self, id: TaskId) -> Option<TaskMetrics> {
+        let inner = self.metrics.inner.lock().unwrap();
+        inner.tasks.get(&id).map(|task| task.metrics(id))
+    }
+
+    pub fn deadline_stats(&self) -> DeadlineStats {
+        self.metrics.inner.lock().unwrap().deadlines
+    }
+}
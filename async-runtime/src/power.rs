@@ -0,0 +1,101 @@
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use serde::Serialize;
+
+use crate::metrics::Metrics;
+
+/// Estimates CPU energy from how long the executor ran and slept.
+pub trait PowerModel: Send + Sync {
+    /// Power drawn while running tasks, in mW.
+    fn active_mw(&self) -> f64;
+    /// Power drawn while sleeping on an empty run queue, in mW.
+    fn sleep_mw(&self) -> f64;
+    /// Energy spent waking up from sleep, in µJ.
+    fn wakeup_uj(&self) -> f64;
+
+    fn estimate(&self, active: Duration, idle: Duration, wakeups: u64) -> EnergyEstimate {
+        // mW * s = mJ
+        let active_mj = self.active_mw() * active.as_secs_f64();
+        let sleep_mj = self.sleep_mw() * idle.as_secs_f64();
+        let wakeup_mj = self.wakeup_uj() * wakeups as f64 / 1000.0;
+        EnergyEstimate {
+            active_mj,
+            sleep_mj,
+            wakeup_mj,
+            total_mj: active_mj + sleep_mj + wakeup_mj,
+        }
+    }
+}
+
+/// A CPU with fixed active and sleep power, and a fixed wake-up cost.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ConstantPowerModel {
+    pub active_mw: f64,
+    pub sleep_mw: f64,
+    pub wakeup_uj: f64,
+}
+
+impl PowerModel for ConstantPowerModel {
+    fn active_mw(&self) -> f64 {
+        self.active_mw
+    }
+
+    fn sleep_mw(&self) -> f64 {
+        self.sleep_mw
+    }
+
+    fn wakeup_uj(&self) -> f64 {
+        self.wakeup_uj
+    }
+}
+
+/// Estimated CPU energy, in mJ.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize)]
+pub struct EnergyEstimate {
+    pub active_mj: f64,
+    pub sleep_mj: f64,
+    pub wakeup_mj: f64,
+    pub total_mj: f64,
+}
+
+/// Splits one executor thread's time into active and idle spans, and
+/// reports them to the metrics store.
+pub(crate) struct Activity {
+    metrics: Arc<Metrics>,
+    active_since: Option<Instant>,
+}
+
+impl Activity {
+    pub(crate) fn new(metrics: Arc<Metrics>) -> Self {
+        Activity {
+            metrics,
+            active_since: None,
+        }
+    }
+
+    /// The thread starts running tasks.
+    pub(crate) fn resume(&mut self) {
+        self.active_since.get_or_insert_with(Instant::now);
+    }
+
+    /// The thread stops running tasks, e.g. because `block_on` returned.
+    pub(crate) fn suspend(&mut self) {
+        if let Some(active_since) = self.active_since.take() {
+            self.metrics.record_active(active_since.elapsed());
+        }
+    }
+
+    /// Runs `sleep`, which blocks until there is work, counting it as idle
+    /// time followed by a wake-up.
+    pub(crate) fn sleep<R>(&mut self, sleep: impl FnOnce() -> R) -> R {
+        self.suspend();
+        let idle_since = Instant::now();
+        let result = sleep();
+        self.metrics.record_idle(idle_since.elapsed());
+        self.resume();
+        result
+    }
+}
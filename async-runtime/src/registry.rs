@@ -0,0 +1,149 @@
+use std::{
+    any::Any,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+
+use futures::future::BoxFuture;
+
+use crate::task::{JoinHandle, Spawner};
+
+/// What happens to a registered task when the runtime reboots.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RestartPolicy {
+    /// Started afresh on every boot; its checkpoint is discarded.
+    Always,
+    /// Restarted on every boot until it runs to completion, seeing the
+    /// last checkpoint it saved.
+    FromCheckpoint,
+    /// Started on the first boot only.
+    RunOnce,
+}
+
+type Checkpoint = Arc<Mutex<Option<Box<dyn Any + Send>>>>;
+
+type TaskFactory = Arc<dyn Fn(TaskContext) -> BoxFuture<'static, ()> + Send + Sync>;
+
+/// Handed to a registered task each time it is started. Checkpoints saved
+/// through it outlive reboots, unlike anything else the task holds.
+#[derive(Clone)]
+pub struct TaskContext {
+    name: Arc<str>,
+    boot: u64,
+    checkpoint: Checkpoint,
+}
+
+impl TaskContext {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// How many reboots happened before this start; 0 on the first boot.
+    pub fn boot(&self) -> u64 {
+        self.boot
+    }
+
+    /// The last checkpoint saved by this task, if it has the type `T`.
+    pub fn checkpoint<T: Clone + 'static>(&self) -> Option<T> {
+        self.checkpoint
+            .lock()
+            .unwrap()
+            .as_ref()?
+            .downcast_ref::<T>()
+            .cloned()
+    }
+
+    /// Saves progress for the task to resume from after a reboot.
+    pub fn save_checkpoint<T: Send + 'static>(&self, value: T) {
+        *self.checkpoint.lock().unwrap() = Some(Box::new(value));
+    }
+}
+
+struct Entry {
+    name: Arc<str>,
+    policy: RestartPolicy,
+    factory: TaskFactory,
+
+    // Survive reboots
+    checkpoint: Checkpoint,
+    completed: Arc<AtomicBool>,
+}
+
+/// Tasks spawned by name, to be re-spawned on reboot. The registry and the
+/// checkpoints it holds stand in for non-volatile memory.
+pub(crate) struct Registry {
+    entries: Vec<Entry>,
+    boot: u64,
+}
+
+impl Registry {
+    pub(crate) fn new() -> Self {
+        Registry {
+            entries: Vec::new(),
+            boot: 0,
+        }
+    }
+
+    // Number of reboots recorded so far
+    pub(crate) fn record_boot(&self) -> u64 {
+        self.boot
+    }
+
+    pub(crate) fn register(
+        &mut self,
+        spawner: &Spawner,
+        name: &str,
+        policy: RestartPolicy,
+        factory: TaskFactory,
+    ) -> JoinHandle<()> {
+        assert!(
+            self.entries.iter().all(|entry| &*entry.name != name),
+            "a task named {:?} is already registered",
+            name
+        );
+
+        let entry = Entry {
+            name: name.into(),
+            policy,
+            factory,
+            checkpoint: Arc::new(Mutex::new(None)),
+            completed: Arc::new(AtomicBool::new(false)),
+        };
+        let handle = self.start(spawner, &entry);
+        self.entries.push(entry);
+        handle
+    }
+
+    /// Starts the next boot: re-spawns registered tasks according to their
+    /// policies. The caller has already dropped the previous boot's tasks.
+    pub(crate) fn reboot(&mut self, spawner: &Spawner) {
+        self.boot += 1;
+        for entry in &self.entries {
+            match entry.policy {
+                RestartPolicy::Always => {
+                    entry.checkpoint.lock().unwrap().take();
+                }
+                RestartPolicy::FromCheckpoint if !entry.completed.load(Ordering::Acquire) => (),
+                RestartPolicy::FromCheckpoint | RestartPolicy::RunOnce => continue,
+            }
+            self.start(spawner, entry);
+        }
+    }
+
+    fn start(&self, spawner: &Spawner, entry: &Entry) -> JoinHandle<()> {
+        let context = TaskContext {
+            name: entry.name.clone(),
+            boot: self.boot,
+            checkpoint: entry.checkpoint.clone(),
+        };
+        let future = (entry.factory)(context);
+        let completed = entry.completed.clone();
+
+        spawner.spawn(async move {
+            future.await;
+            completed.store(true, Ordering::Release);
+        })
+    }
+}
@@ -0,0 +1,156 @@
+use std::{
+    fmt,
+    panic::AssertUnwindSafe,
+    pin::Pin,
+    sync::Mutex,
+    task::{Context, Poll},
+};
+
+use futures::{FutureExt, StreamExt, future::BoxFuture, stream::FuturesUnordered};
+
+use crate::task::{PanicPayload, panic_message};
+
+/// Why a scope did not complete.
+#[derive(Debug)]
+pub enum ScopeError<E> {
+    /// The scope body or a child task returned an error.
+    Failed(E),
+    /// The scope body or a child task panicked.
+    Panicked(PanicPayload),
+}
+
+impl<E: fmt::Display> fmt::Display for ScopeError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScopeError::Failed(error) => write!(f, "scoped task failed: {}", error),
+            ScopeError::Panicked(payload) => {
+                write!(f, "scoped task panicked: {}", panic_message(payload))
+            }
+        }
+    }
+}
+
+impl<E: fmt::Debug + fmt::Display> std::error::Error for ScopeError<E> {}
+
+// A child's result, or its panic
+type Child<'env, E> = BoxFuture<'env, Result<Result<(), E>, PanicPayload>>;
+
+/// A nursery for child tasks that may borrow from the enclosing function.
+/// See `scope`.
+pub struct TaskScope<'env, E> {
+    // Spawned but not yet picked up by the scope future
+    spawned: Mutex<Vec<Child<'env, E>>>,
+}
+
+impl<'env, E> TaskScope<'env, E> {
+    /// Starts `future` as a child of this scope. It runs concurrently with
+    /// the scope body and its siblings, and the scope does not complete
+    /// before it does.
+    pub fn spawn(&self, future: impl Future<Output = Result<(), E>> + Send + 'env) {
+        let child = AssertUnwindSafe(future).catch_unwind().boxed();
+        self.spawned.lock().unwrap().push(child);
+    }
+}
+
+// Children are polled by the scope's own future (not spawned on the
+// executor), which is what lets them borrow non-'static data soundly: they
+// cannot outlive the scope, because dropping the scope drops them.
+struct ScopeFuture<'scope, 'env, R, E> {
+    scope: &'scope TaskScope<'env, E>,
+    body: BoxFuture<'scope, Result<R, E>>,
+    output: Option<R>,
+    children: FuturesUnordered<Child<'env, E>>,
+}
+
+// Nothing in the scope future is ever pinned in place
+impl<R, E> Unpin for ScopeFuture<'_, '_, R, E> {}
+
+impl<R, E> ScopeFuture<'_, '_, R, E> {
+    // Moves newly spawned children into the running set; false if there were none
+    fn adopt_children(&mut self) -> bool {
+        let spawned: Vec<_> = self.scope.spawned.lock().unwrap().drain(..).collect();
+        let adopted = !spawned.is_empty();
+        self.children.extend(spawned);
+        adopted
+    }
+}
+
+impl<R, E> Future for ScopeFuture<'_, '_, R, E> {
+    type Output = Result<R, ScopeError<E>>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // Returning early drops the remaining children, cancelling them
+        loop {
+            if self.output.is_none() {
+                let body = AssertUnwindSafe(|| self.body.as_mut().poll(cx));
+                match std::panic::catch_unwind(body) {
+                    Ok(Poll::Ready(Ok(output))) => self.output = Some(output),
+                    Ok(Poll::Ready(Err(error))) => {
+                        return Poll::Ready(Err(ScopeError::Failed(error)));
+                    }
+                    Ok(Poll::Pending) => (),
+                    Err(payload) => return Poll::Ready(Err(ScopeError::Panicked(payload))),
+                }
+            }
+
+            self.adopt_children();
+            while let Poll::Ready(Some(result)) = self.children.poll_next_unpin(cx) {
+                match result {
+                    Ok(Ok(())) => (),
+                    Ok(Err(error)) => return Poll::Ready(Err(ScopeError::Failed(error))),
+                    Err(payload) => return Poll::Ready(Err(ScopeError::Panicked(payload))),
+                }
+            }
+
+            // Children may have spawned siblings; those need a first poll too
+            if !self.adopt_children() {
+                break;
+            }
+        }
+
+        if self.children.is_empty()
+            && let Some(output) = self.output.take()
+        {
+            return Poll::Ready(Ok(output));
+        }
+        Poll::Pending
+    }
+}
+
+/// Runs `body` with a `TaskScope` for spawning child tasks, and completes
+/// once the body and every child have completed.
+///
+/// Children may borrow anything that outlives the call. If the body or any
+/// child fails (returns `Err` or panics), the remaining children are
+/// cancelled and the failure is returned. If the future returned by `scope`
+/// is dropped (e.g. because its task was aborted), the children are dropped
+/// with it.
+///
+/// ```ignore
+/// let mut readings = [0.0; 3];
+/// let slots = &mut readings;
+/// scope(move |s| {
+///     for slot in slots {
+///         s.spawn(async move { *slot = read_sensor().await?; Ok(()) });
+///     }
+///     async { Ok(()) }.boxed()
+/// })
+/// .await?;
+/// ```
+pub async fn scope<'env, R, E, F>(body: F) -> Result<R, ScopeError<E>>
+where
+    F: for<'scope> FnOnce(&'scope TaskScope<'env, E>) -> BoxFuture<'scope, Result<R, E>>,
+{
+    let task_scope = TaskScope {
+        spawned: Mutex::new(Vec::new()),
+    };
+    let body = body(&task_scope);
+
+    ScopeFuture {
+        scope: &task_scope,
+        body,
+        output: None,
+        children: FuturesUnordered::new(),
+    }
+    .await
+}
@@ -0,0 +1,78 @@
+use std::{
+    ops::Range,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use futures::Stream;
+use rand::Rng;
+use serde::Serialize;
+
+use super::{SampleBuffer, Sensor};
+
+const PERIOD: Range<Duration> = Duration::from_millis(5)..Duration::from_millis(15);
+const GRAVITY: f64 = 9.81;
+
+/// One accelerometer sample, in m/s² along each axis.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize)]
+pub struct Acceleration {
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+}
+
+impl Acceleration {
+    pub fn magnitude(&self) -> f64 {
+        (self.x * self.x + self.y * self.y + self.z * self.z).sqrt()
+    }
+}
+
+/// A simulated 3-axis accelerometer lying flat: gravity along z plus
+/// noise, with the occasional knock.
+pub struct Accelerometer {
+    buffer: SampleBuffer<Acceleration>,
+}
+
+impl Accelerometer {
+    pub fn new() -> Self {
+        let buffer = super::simulate(PERIOD, |rng| {
+            let jolt = if rng.random_bool(0.01) { 5.0 } else { 0.0 };
+            let mut noise = || rng.random_range(-0.1..0.1) * (1.0 + jolt);
+            Acceleration {
+                x: noise(),
+                y: noise(),
+                z: GRAVITY + noise(),
+            }
+        });
+        Accelerometer { buffer }
+    }
+}
+
+impl Sensor for Accelerometer {
+    fn units(&self) -> &'static str {
+        "m/s²"
+    }
+
+    fn sampling_period(&self) -> Duration {
+        super::mean(&PERIOD)
+    }
+
+    fn poll_read(&mut self, cx: &mut Context<'_>, max: usize) -> Poll<Vec<Acceleration>> {
+        self.buffer.poll_take(cx, max)
+    }
+}
+
+impl Stream for Accelerometer {
+    type Item = Acceleration;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Acceleration>> {
+        self.poll_read(cx, 1).map(|mut samples| samples.pop())
+    }
+}
+
+impl Default for Accelerometer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,58 @@
+use std::{
+    ops::Range,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use futures::Stream;
+use rand::Rng;
+
+use super::{SampleBuffer, Sensor};
+
+const PERIOD: Range<Duration> = Duration::from_secs(2)..Duration::from_secs(4);
+
+/// A simulated relative humidity sensor, in %. Readings drift slowly
+/// rather than jumping around.
+pub struct HumiditySensor {
+    buffer: SampleBuffer<f64>,
+}
+
+impl HumiditySensor {
+    pub fn new() -> Self {
+        let mut humidity: f64 = 50.0;
+        let buffer = super::simulate(PERIOD, move |rng| {
+            humidity = (humidity + rng.random_range(-2.0..2.0)).clamp(20.0, 90.0);
+            humidity
+        });
+        HumiditySensor { buffer }
+    }
+}
+
+impl Sensor for HumiditySensor {
+    fn units(&self) -> &'static str {
+        "%RH"
+    }
+
+    fn sampling_period(&self) -> Duration {
+        super::mean(&PERIOD)
+    }
+
+    fn poll_read(&mut self, cx: &mut Context<'_>, max: usize) -> Poll<Vec<f64>> {
+        self.buffer.poll_take(cx, max)
+    }
+}
+
+impl Stream for HumiditySensor {
+    type Item = f64;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<f64>> {
+        self.poll_read(cx, 1).map(|mut samples| samples.pop())
+    }
+}
+
+impl Default for HumiditySensor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,59 @@
+use std::{
+    ops::Range,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use futures::Stream;
+use rand::Rng;
+
+use super::{SampleBuffer, Sensor};
+
+const PERIOD: Range<Duration> = Duration::from_millis(500)..Duration::from_millis(1500);
+
+/// A simulated ambient light sensor, in lux. Readings wander between
+/// moonlight (about 0.1 lx) and direct sunlight (about 100000 lx).
+pub struct LightSensor {
+    buffer: SampleBuffer<f64>,
+}
+
+impl LightSensor {
+    pub fn new() -> Self {
+        // Light levels span orders of magnitude, so the walk is in log10
+        let mut level: f64 = 2.5;
+        let buffer = super::simulate(PERIOD, move |rng| {
+            level = (level + rng.random_range(-0.2..0.2)).clamp(-1.0, 5.0);
+            10f64.powf(level)
+        });
+        LightSensor { buffer }
+    }
+}
+
+impl Sensor for LightSensor {
+    fn units(&self) -> &'static str {
+        "lx"
+    }
+
+    fn sampling_period(&self) -> Duration {
+        super::mean(&PERIOD)
+    }
+
+    fn poll_read(&mut self, cx: &mut Context<'_>, max: usize) -> Poll<Vec<f64>> {
+        self.buffer.poll_take(cx, max)
+    }
+}
+
+impl Stream for LightSensor {
+    type Item = f64;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<f64>> {
+        self.poll_read(cx, 1).map(|mut samples| samples.pop())
+    }
+}
+
+impl Default for LightSensor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,198 @@
+use std::{
+    collections::VecDeque,
+    ops::Range,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll, Waker},
+    thread,
+    time::Duration,
+};
+
+use futures::Stream;
+use rand::{Rng, SeedableRng, rngs::SmallRng};
+
+use crate::coop;
+
+mod accelerometer;
+mod humidity;
+mod light;
+mod trace;
+
+pub use accelerometer::{Acceleration, Accelerometer};
+pub use humidity::HumiditySensor;
+pub use light::LightSensor;
+pub use trace::{PlaybackOptions, Trace, TraceError, TraceSensor};
+
+/// A source of samples. As a `Stream` a sensor yields its samples one by
+/// one, in the order they were taken; it only ends if the sensor runs out
+/// of samples, like a trace played back once. Reading borrows the sensor
+/// mutably, so there is only ever one reader.
+pub trait Sensor: Stream + Unpin {
+    /// The unit samples are expressed in, e.g. "°C".
+    fn units(&self) -> &'static str;
+
+    /// The average time between samples.
+    fn sampling_period(&self) -> Duration;
+
+    /// Takes up to `max` buffered samples, or registers to be woken when
+    /// one arrives if there are none. An empty batch means the sensor has
+    /// run out.
+    fn poll_read(&mut self, cx: &mut Context<'_>, max: usize) -> Poll<Vec<Self::Item>>;
+
+    /// Waits for the next batch of samples: every sample buffered since the
+    /// last read, at least one unless the sensor has run out.
+    fn read(&mut self) -> Read<'_, Self>
+    where
+        Self: Sized,
+    {
+        Read { sensor: self }
+    }
+
+    /// The samples as a stream of batches, each holding everything taken
+    /// since the previous one.
+    fn batches(&mut self) -> Batches<'_, Self>
+    where
+        Self: Sized,
+    {
+        Batches { sensor: self }
+    }
+}
+
+/// The type of the samples a sensor produces.
+pub type Sample<S> = <S as Stream>::Item;
+
+/// Future returned by `Sensor::read`.
+pub struct Read<'a, S> {
+    sensor: &'a mut S,
+}
+
+impl<S: Sensor> Future for Read<'_, S> {
+    type Output = Vec<S::Item>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.sensor.poll_read(cx, usize::MAX)
+    }
+}
+
+/// Stream returned by `Sensor::batches`.
+pub struct Batches<'a, S> {
+    sensor: &'a mut S,
+}
+
+impl<S: Sensor> Stream for Batches<'_, S> {
+    type Item = Vec<S::Item>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let batch = self.sensor.poll_read(cx, usize::MAX);
+        batch.map(|batch| (!batch.is_empty()).then_some(batch))
+    }
+}
+
+struct Buffered<T> {
+    samples: VecDeque<T>,
+    waker: Option<Waker>,
+    // No more samples will be pushed
+    closed: bool,
+    // Samples pushed while disabled are dropped, as if never taken
+    enabled: bool,
+}
+
+// Samples taken by a simulated sensor's thread, waiting to be read
+pub(crate) struct SampleBuffer<T> {
+    shared: Arc<Mutex<Buffered<T>>>,
+}
+
+impl<T> SampleBuffer<T> {
+    pub(crate) fn new() -> Self {
+        SampleBuffer {
+            shared: Arc::new(Mutex::new(Buffered {
+                samples: VecDeque::new(),
+                waker: None,
+                closed: false,
+                enabled: true,
+            })),
+        }
+    }
+
+    pub(crate) fn push(&self, sample: T) {
+        let mut shared = self.shared.lock().unwrap();
+        if !shared.enabled {
+            return;
+        }
+        shared.samples.push_back(sample);
+        if let Some(waker) = shared.waker.take() {
+            waker.wake();
+        }
+    }
+
+    pub(crate) fn set_enabled(&self, enabled: bool) {
+        self.shared.lock().unwrap().enabled = enabled;
+    }
+
+    // Drops the samples not read yet
+    pub(crate) fn clear(&self) {
+        self.shared.lock().unwrap().samples.clear();
+    }
+
+    // Lets the reader drain what is left, then see an empty batch
+    pub(crate) fn close(&self) {
+        let mut shared = self.shared.lock().unwrap();
+        shared.closed = true;
+        if let Some(waker) = shared.waker.take() {
+            waker.wake();
+        }
+    }
+
+    pub(crate) fn poll_take(&self, cx: &mut Context<'_>, max: usize) -> Poll<Vec<T>> {
+        coop::cooperative(cx, |cx| {
+            let mut shared = self.shared.lock().unwrap();
+            if shared.samples.is_empty() && !shared.closed {
+                shared.waker = Some(cx.waker().clone());
+                return Poll::Pending;
+            }
+
+            let count = max.min(shared.samples.len());
+            shared.waker = None;
+            Poll::Ready(shared.samples.drain(..count).collect())
+        })
+    }
+
+    // Whether anything besides the caller still holds the buffer
+    pub(crate) fn is_shared(&self) -> bool {
+        Arc::strong_count(&self.shared) > 1
+    }
+}
+
+impl<T> Clone for SampleBuffer<T> {
+    fn clone(&self) -> Self {
+        SampleBuffer {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+/// Starts a thread sampling `sample` at random intervals drawn from
+/// `period`, until the returned buffer is dropped.
+pub(crate) fn simulate<T: Send + 'static>(
+    period: Range<Duration>,
+    mut sample: impl FnMut(&mut SmallRng) -> T + Send + 'static,
+) -> SampleBuffer<T> {
+    let buffer = SampleBuffer::new();
+
+    let thread_buffer = buffer.clone();
+    thread::spawn(move || {
+        let mut rng = SmallRng::from_os_rng();
+
+        while thread_buffer.is_shared() {
+            thread::sleep(rng.random_range(period.clone()));
+            thread_buffer.push(sample(&mut rng));
+        }
+    });
+
+    buffer
+}
+
+// The midpoint of a sampling period range
+pub(crate) fn mean(period: &Range<Duration>) -> Duration {
+    (period.start + period.end) / 2
+}
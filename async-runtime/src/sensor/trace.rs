@@ -0,0 +1,291 @@
+use std::{
+    fmt,
+    path::Path,
+    pin::Pin,
+    task::{Context, Poll},
+    thread,
+    time::Duration,
+};
+
+use futures::{Stream, executor::block_on};
+use serde::de::DeserializeOwned;
+
+use super::{SampleBuffer, Sensor};
+use crate::{clock, timer::sleep_until};
+
+/// Why a trace could not be loaded.
+#[derive(Debug)]
+pub enum TraceError {
+    /// The file could not be read, or a row could not be parsed.
+    Csv(csv::Error),
+    /// The timestamp of this row (counting from 0) is negative or not a
+    /// number.
+    NegativeTimestamp(usize),
+    /// The timestamp of this sample (counting from 0) is earlier than the
+    /// one before it.
+    OutOfOrder(usize),
+    /// The trace has no samples.
+    Empty,
+}
+
+impl fmt::Display for TraceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TraceError::Csv(error) => write!(f, "invalid trace: {}", error),
+            TraceError::NegativeTimestamp(index) => {
+                write!(f, "timestamp of sample {} is negative", index)
+            }
+            TraceError::OutOfOrder(index) => {
+                write!(f, "timestamp of sample {} is out of order", index)
+            }
+            TraceError::Empty => write!(f, "trace has no samples"),
+        }
+    }
+}
+
+impl std::error::Error for TraceError {}
+
+impl From<csv::Error> for TraceError {
+    fn from(error: csv::Error) -> Self {
+        TraceError::Csv(error)
+    }
+}
+
+/// Samples recorded in the field, each with the time it was taken at
+/// relative to the start of the recording. Sensors built from a trace
+/// replay it instead of making samples up, so runs are reproducible.
+#[derive(Clone, Debug)]
+pub struct Trace<T> {
+    samples: Vec<(Duration, T)>,
+}
+
+impl<T> Trace<T> {
+    pub fn new(samples: Vec<(Duration, T)>) -> Result<Self, TraceError> {
+        if samples.is_empty() {
+            return Err(TraceError::Empty);
+        }
+        if let Some(index) = samples.windows(2).position(|pair| pair[1].0 < pair[0].0) {
+            return Err(TraceError::OutOfOrder(index + 1));
+        }
+        Ok(Trace { samples })
+    }
+
+    /// Loads a CSV file with a header row and `timestamp,value` columns,
+    /// timestamps being in seconds.
+    pub fn from_csv(path: impl AsRef<Path>) -> Result<Self, TraceError>
+    where
+        T: DeserializeOwned,
+    {
+        let mut reader = csv::Reader::from_path(path)?;
+        let mut samples = Vec::new();
+        for (index, row) in reader.deserialize().enumerate() {
+            let (seconds, value): (f64, T) = row?;
+            let timestamp = Duration::try_from_secs_f64(seconds)
+                .map_err(|_| TraceError::NegativeTimestamp(index))?;
+            samples.push((timestamp, value));
+        }
+        Self::new(samples)
+    }
+
+    pub fn samples(&self) -> &[(Duration, T)] {
+        &self.samples
+    }
+
+    /// The average time between samples; `None` for a single sample.
+    pub fn mean_interval(&self) -> Option<Duration> {
+        let first = self.samples[0].0;
+        let last = self.samples[self.samples.len() - 1].0;
+        match self.samples.len() {
+            1 => None,
+            len => Some((last - first) / (len as u32 - 1)),
+        }
+    }
+
+    /// How long one pass over the trace lasts: up to the last sample, plus
+    /// one average interval, so a looped trace keeps its pace at the seam.
+    pub fn duration(&self) -> Duration {
+        self.samples[self.samples.len() - 1].0 + self.mean_interval().unwrap_or_default()
+    }
+}
+
+/// How a trace is played back.
+#[derive(Clone, Copy, Debug)]
+pub struct PlaybackOptions {
+    /// How much faster than recorded the trace plays; 2.0 replays it in
+    /// half the time. Must be positive and finite.
+    pub speed: f64,
+    /// Start over after the last sample, rather than ending the stream.
+    pub looped: bool,
+}
+
+impl Default for PlaybackOptions {
+    fn default() -> Self {
+        PlaybackOptions {
+            speed: 1.0,
+            looped: false,
+        }
+    }
+}
+
+impl<T: Clone + Send + 'static> Trace<T> {
+    /// Starts a thread pushing the samples at their recorded times, until
+    /// the trace ends or the returned buffer is dropped. Times are kept on
+    /// the installed clock, so a `VirtualClock` drives playback.
+    pub(crate) fn play(self, options: PlaybackOptions) -> SampleBuffer<T> {
+        assert!(
+            options.speed.is_finite() && options.speed > 0.0,
+            "playback speed must be positive and finite, got {}",
+            options.speed
+        );
+        let buffer = SampleBuffer::new();
+
+        let thread_buffer = buffer.clone();
+        thread::spawn(move || {
+            let start = clock::now();
+            let duration = self.duration();
+            // A trace that takes no time would loop without ever sleeping
+            let looped = options.looped && !duration.is_zero();
+
+            let mut pass = Duration::ZERO;
+            loop {
+                for (timestamp, sample) in &self.samples {
+                    let due = start + (pass + *timestamp).div_f64(options.speed);
+                    block_on(sleep_until(due));
+                    if !thread_buffer.is_shared() {
+                        return;
+                    }
+                    thread_buffer.push(sample.clone());
+                }
+
+                if !looped {
+                    thread_buffer.close();
+                    return;
+                }
+                pass += duration;
+            }
+        });
+
+        buffer
+    }
+}
+
+/// A sensor replaying a trace of any kind of sample, e.g. humidity or
+/// acceleration recorded in the field. Unless looped, its stream ends with
+/// the trace.
+pub struct TraceSensor<T> {
+    units: &'static str,
+    buffer: SampleBuffer<T>,
+    period: Duration,
+}
+
+impl<T: Clone + Send + 'static> TraceSensor<T> {
+    /// A single-sample trace reports a zero sampling period.
+    pub fn new(trace: Trace<T>, units: &'static str, options: PlaybackOptions) -> Self {
+        let interval = trace.mean_interval().unwrap_or_default();
+        let buffer = trace.play(options);
+
+        TraceSensor {
+            units,
+            buffer,
+            period: interval.div_f64(options.speed),
+        }
+    }
+}
+
+impl<T> Sensor for TraceSensor<T> {
+    fn units(&self) -> &'static str {
+        self.units
+    }
+
+    fn sampling_period(&self) -> Duration {
+        self.period
+    }
+
+    fn poll_read(&mut self, cx: &mut Context<'_>, max: usize) -> Poll<Vec<T>> {
+        self.buffer.poll_take(cx, max)
+    }
+}
+
+impl<T> Stream for TraceSensor<T> {
+    type Item = T;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        self.poll_read(cx, 1).map(|mut samples| samples.pop())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::PathBuf};
+
+    use super::*;
+
+    // Writes `contents` to a file unique to this test
+    fn csv_file(name: &str, contents: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("trace-{}-{}.csv", std::process::id(), name));
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    fn load(name: &str, contents: &str) -> Result<Trace<f64>, TraceError> {
+        let path = csv_file(name, contents);
+        let trace = Trace::from_csv(&path);
+        fs::remove_file(path).unwrap();
+        trace
+    }
+
+    #[test]
+    fn loads_samples() {
+        let trace = load("valid", "timestamp,value\n0,1.5\n0.5,2\n1,2.5\n").unwrap();
+        assert_eq!(
+            trace.samples(),
+            [
+                (Duration::ZERO, 1.5),
+                (Duration::from_millis(500), 2.0),
+                (Duration::from_secs(1), 2.5)
+            ]
+        );
+        assert_eq!(trace.mean_interval(), Some(Duration::from_millis(500)));
+        assert_eq!(trace.duration(), Duration::from_millis(1500));
+    }
+
+    #[test]
+    fn rejects_malformed_rows() {
+        let error = load("malformed", "timestamp,value\n0,1.5\n0.5,warm\n").unwrap_err();
+        assert!(matches!(error, TraceError::Csv(_)), "{:?}", error);
+
+        let error = load("short", "timestamp,value\n0,1.5\n0.5\n").unwrap_err();
+        assert!(matches!(error, TraceError::Csv(_)), "{:?}", error);
+    }
+
+    #[test]
+    fn rejects_negative_timestamps() {
+        let error = load("negative", "timestamp,value\n0,1.5\n-1,2\n").unwrap_err();
+        assert!(
+            matches!(error, TraceError::NegativeTimestamp(1)),
+            "{:?}",
+            error
+        );
+    }
+
+    #[test]
+    fn rejects_out_of_order_rows() {
+        let error = load("out-of-order", "timestamp,value\n0,1.5\n2,2\n1,2.5\n").unwrap_err();
+        assert!(matches!(error, TraceError::OutOfOrder(2)), "{:?}", error);
+    }
+
+    #[test]
+    fn rejects_empty_files() {
+        for (name, contents) in [("empty", ""), ("header-only", "timestamp,value\n")] {
+            let error = load(name, contents).unwrap_err();
+            assert!(matches!(error, TraceError::Empty), "{:?}", error);
+        }
+    }
+
+    #[test]
+    fn single_sample_has_no_interval() {
+        let trace = Trace::new(vec![(Duration::from_secs(2), 1.0)]).unwrap();
+        assert_eq!(trace.mean_interval(), None);
+        assert_eq!(trace.duration(), Duration::from_secs(2));
+    }
+}
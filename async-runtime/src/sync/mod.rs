@@ -0,0 +1,74 @@
+// Async-aware synchronization primitives. Waiting tasks yield to the
+// executor instead of blocking its thread, and are served in FIFO order.
+
+use std::{collections::VecDeque, task::Waker};
+
+pub mod mpsc;
+mod mutex;
+mod notify;
+pub mod oneshot;
+mod semaphore;
+
+pub use mutex::{Mutex, MutexGuard};
+pub use notify::{Notified, Notify};
+pub use semaphore::{Acquire, AcquireError, Semaphore, SemaphorePermit, TryAcquireError};
+
+struct Waiter<T> {
+    id: u64,
+    data: T,
+    waker: Waker,
+}
+
+// Tasks waiting on a primitive, oldest first. Futures keep the id they were
+// given so they can update their waker or leave the list when dropped.
+struct WaitList<T> {
+    next_id: u64,
+    waiters: VecDeque<Waiter<T>>,
+}
+
+impl<T> WaitList<T> {
+    fn new() -> Self {
+        WaitList {
+            next_id: 0,
+            waiters: VecDeque::new(),
+        }
+    }
+
+    fn push(&mut self, data: T, waker: &Waker) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.waiters.push_back(Waiter {
+            id,
+            data,
+            waker: waker.clone(),
+        });
+        id
+    }
+
+    fn update(&mut self, id: u64, waker: &Waker) {
+        if let Some(waiter) = self.waiters.iter_mut().find(|waiter| waiter.id == id)
+            && !waiter.waker.will_wake(waker)
+        {
+            waiter.waker = waker.clone();
+        }
+    }
+
+    fn remove(&mut self, id: u64) -> Option<T> {
+        let index = self.waiters.iter().position(|waiter| waiter.id == id)?;
+        self.waiters.remove(index).map(|waiter| waiter.data)
+    }
+
+    fn front(&self) -> Option<&T> {
+        self.waiters.front().map(|waiter| &waiter.data)
+    }
+
+    fn pop_front(&mut self) -> Option<(u64, Waker)> {
+        self.waiters
+            .pop_front()
+            .map(|waiter| (waiter.id, waiter.waker))
+    }
+
+    fn is_empty(&self) -> bool {
+        self.waiters.is_empty()
+    }
+}
@@ -0,0 +1,339 @@
+use std::{
+    collections::VecDeque,
+    fmt,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll, Waker},
+};
+
+use futures::{Stream, future::poll_fn};
+
+use super::{Semaphore, TryAcquireError};
+use crate::coop;
+
+/// Returned when sending on a channel whose receiver is gone; holds the
+/// value that could not be sent.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SendError<T>(pub T);
+
+impl<T> fmt::Display for SendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "channel closed")
+    }
+}
+
+impl<T: fmt::Debug> std::error::Error for SendError<T> {}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TrySendError<T> {
+    Full(T),
+    Closed(T),
+}
+
+impl<T> fmt::Display for TrySendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TrySendError::Full(_) => write!(f, "channel full"),
+            TrySendError::Closed(_) => write!(f, "channel closed"),
+        }
+    }
+}
+
+impl<T: fmt::Debug> std::error::Error for TrySendError<T> {}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TryRecvError {
+    Empty,
+    Disconnected,
+}
+
+struct State<T> {
+    queue: VecDeque<T>,
+    receiver: Option<Waker>,
+    senders: usize,
+    closed: bool,
+}
+
+struct Chan<T> {
+    state: Mutex<State<T>>,
+    // Free slots, for bounded channels; senders wait on it in FIFO order
+    capacity: Option<Semaphore>,
+}
+
+impl<T> Chan<T> {
+    fn new(capacity: Option<usize>) -> Arc<Self> {
+        Arc::new(Chan {
+            state: Mutex::new(State {
+                queue: VecDeque::new(),
+                receiver: None,
+                senders: 1,
+                closed: false,
+            }),
+            capacity: capacity.map(Semaphore::new),
+        })
+    }
+
+    fn push(&self, value: T) -> Result<(), SendError<T>> {
+        let mut state = self.state.lock().unwrap();
+        if state.closed {
+            return Err(SendError(value));
+        }
+        state.queue.push_back(value);
+        let waker = state.receiver.take();
+        drop(state);
+
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+        Ok(())
+    }
+}
+
+// The sending half shared by `Sender` and `UnboundedSender`
+struct Tx<T> {
+    chan: Arc<Chan<T>>,
+}
+
+impl<T> Clone for Tx<T> {
+    fn clone(&self) -> Self {
+        self.chan.state.lock().unwrap().senders += 1;
+        Tx {
+            chan: self.chan.clone(),
+        }
+    }
+}
+
+impl<T> Drop for Tx<T> {
+    fn drop(&mut self) {
+        let mut state = self.chan.state.lock().unwrap();
+        state.senders -= 1;
+        let waker = if state.senders == 0 {
+            state.receiver.take()
+        } else {
+            None
+        };
+        drop(state);
+
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+/// Creates a channel holding at most `capacity` values. Senders wait for
+/// room, and are let in in the order they started waiting.
+pub fn channel<T>(capacity: usize) -> (Sender<T>, Receiver<T>) {
+    assert!(
+        capacity > 0,
+        "a bounded channel needs room for at least one value"
+    );
+
+    let chan = Chan::new(Some(capacity));
+    (
+        Sender {
+            tx: Tx { chan: chan.clone() },
+        },
+        Receiver { chan },
+    )
+}
+
+/// Creates a channel with no limit on how many values it holds.
+pub fn unbounded_channel<T>() -> (UnboundedSender<T>, Receiver<T>) {
+    let chan = Chan::new(None);
+    (
+        UnboundedSender {
+            tx: Tx { chan: chan.clone() },
+        },
+        Receiver { chan },
+    )
+}
+
+pub struct Sender<T> {
+    tx: Tx<T>,
+}
+
+// Not derived, which would require `T: Clone`
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Self {
+        Sender {
+            tx: self.tx.clone(),
+        }
+    }
+}
+
+impl<T> Sender<T> {
+    /// Sends `value`, waiting for room in the channel if it is full.
+    pub async fn send(&self, value: T) -> Result<(), SendError<T>> {
+        let chan = &self.tx.chan;
+        match chan.capacity.as_ref().unwrap().acquire().await {
+            // The slot is given back by the receiver when it takes the value
+            Ok(permit) => permit.forget(),
+            Err(_) => return Err(SendError(value)),
+        }
+        chan.push(value)
+    }
+
+    pub fn try_send(&self, value: T) -> Result<(), TrySendError<T>> {
+        let chan = &self.tx.chan;
+        match chan.capacity.as_ref().unwrap().try_acquire() {
+            Ok(permit) => permit.forget(),
+            Err(TryAcquireError::NoPermits) => return Err(TrySendError::Full(value)),
+            Err(TryAcquireError::Closed) => return Err(TrySendError::Closed(value)),
+        }
+        chan.push(value)
+            .map_err(|SendError(value)| TrySendError::Closed(value))
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.tx.chan.state.lock().unwrap().closed
+    }
+}
+
+pub struct UnboundedSender<T> {
+    tx: Tx<T>,
+}
+
+impl<T> Clone for UnboundedSender<T> {
+    fn clone(&self) -> Self {
+        UnboundedSender {
+            tx: self.tx.clone(),
+        }
+    }
+}
+
+impl<T> UnboundedSender<T> {
+    /// Sends `value` without waiting.
+    pub fn send(&self, value: T) -> Result<(), SendError<T>> {
+        self.tx.chan.push(value)
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.tx.chan.state.lock().unwrap().closed
+    }
+}
+
+/// The receiving half of a bounded or unbounded channel. Also a `Stream`
+/// of the values sent.
+pub struct Receiver<T> {
+    chan: Arc<Chan<T>>,
+}
+
+impl<T> Receiver<T> {
+    /// Receives the next value; `None` once every sender is gone and the
+    /// channel is empty.
+    pub async fn recv(&mut self) -> Option<T> {
+        poll_fn(|cx| self.poll_recv(cx)).await
+    }
+
+    pub fn poll_recv(&mut self, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        coop::cooperative(cx, |cx| {
+            let mut state = self.chan.state.lock().unwrap();
+            if let Some(value) = state.queue.pop_front() {
+                drop(state);
+                if let Some(capacity) = &self.chan.capacity {
+                    capacity.add_permits(1);
+                }
+                Poll::Ready(Some(value))
+            } else if state.senders == 0 || state.closed {
+                Poll::Ready(None)
+            } else {
+                state.receiver = Some(cx.waker().clone());
+
+                Poll::Pending
+            }
+        })
+    }
+
+    pub fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        let mut state = self.chan.state.lock().unwrap();
+        if let Some(value) = state.queue.pop_front() {
+            drop(state);
+            if let Some(capacity) = &self.chan.capacity {
+                capacity.add_permits(1);
+            }
+            Ok(value)
+        } else if state.senders == 0 || state.closed {
+            Err(TryRecvError::Disconnected)
+        } else {
+            Err(TryRecvError::Empty)
+        }
+    }
+
+    /// Stops accepting new values; those already sent can still be received.
+    pub fn close(&mut self) {
+        self.chan.state.lock().unwrap().closed = true;
+        if let Some(capacity) = &self.chan.capacity {
+            capacity.close();
+        }
+    }
+}
+
+impl<T> Stream for Receiver<T> {
+    type Item = T;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        self.poll_recv(cx)
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::FutureExt;
+
+    use super::*;
+
+    #[test]
+    fn values_arrive_in_order() {
+        let (sender, mut receiver) = unbounded_channel();
+        sender.send(1).unwrap();
+        sender.send(2).unwrap();
+        drop(sender);
+
+        assert_eq!(receiver.try_recv(), Ok(1));
+        assert_eq!(receiver.recv().now_or_never(), Some(Some(2)));
+        assert_eq!(receiver.recv().now_or_never(), Some(None));
+    }
+
+    #[test]
+    fn bounded_send_waits_for_room() {
+        let (sender, mut receiver) = channel(1);
+        sender.try_send(1).unwrap();
+        assert_eq!(sender.try_send(2), Err(TrySendError::Full(2)));
+
+        let mut send = Box::pin(sender.send(2));
+        assert!((&mut send).now_or_never().is_none());
+        assert_eq!(receiver.try_recv(), Ok(1));
+        assert_eq!(send.now_or_never(), Some(Ok(())));
+        assert_eq!(receiver.try_recv(), Ok(2));
+    }
+
+    #[test]
+    fn bounded_send_fails_once_receiver_closes() {
+        let (sender, mut receiver) = channel(1);
+        sender.try_send(1).unwrap();
+        let mut waiting = Box::pin(sender.send(2));
+        assert!((&mut waiting).now_or_never().is_none());
+
+        receiver.close();
+        assert_eq!(waiting.now_or_never(), Some(Err(SendError(2))));
+        assert_eq!(sender.send(3).now_or_never(), Some(Err(SendError(3))));
+        assert_eq!(sender.try_send(4), Err(TrySendError::Closed(4)));
+        // Values sent before closing can still be received
+        assert_eq!(receiver.try_recv(), Ok(1));
+        assert_eq!(receiver.try_recv(), Err(TryRecvError::Disconnected));
+    }
+
+    #[test]
+    fn unbounded_send_fails_once_receiver_is_dropped() {
+        let (sender, receiver) = unbounded_channel();
+        drop(receiver);
+        assert!(sender.is_closed());
+        assert_eq!(sender.send(1), Err(SendError(1)));
+    }
+}
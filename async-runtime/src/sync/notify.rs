@@ -0,0 +1,189 @@
+use std::{
+    collections::HashMap,
+    pin::Pin,
+    sync::Mutex,
+    task::{Context, Poll, Waker},
+};
+
+use super::WaitList;
+use crate::coop;
+
+struct State {
+    waiters: WaitList<()>,
+    // Woken waiters that have not seen it yet, and whether it was by `notify_one`
+    notified: HashMap<u64, bool>,
+    // A `notify_one` that found nobody waiting
+    permit: bool,
+}
+
+/// Wakes tasks waiting for an event, without carrying any data.
+pub struct Notify {
+    state: Mutex<State>,
+}
+
+impl Notify {
+    pub fn new() -> Self {
+        Notify {
+            state: Mutex::new(State {
+                waiters: WaitList::new(),
+                notified: HashMap::new(),
+                permit: false,
+            }),
+        }
+    }
+
+    /// Waits for a notification. Only `notify_one` notifications are stored
+    /// for later; `notify_waiters` only reaches futures that have already
+    /// been polled.
+    pub fn notified(&self) -> Notified<'_> {
+        Notified {
+            notify: self,
+            id: None,
+        }
+    }
+
+    /// Wakes the task that has waited longest. If none is waiting, the next
+    /// call to `notified` completes immediately.
+    pub fn notify_one(&self) {
+        let mut state = self.state.lock().unwrap();
+        let Some((id, waker)) = state.waiters.pop_front() else {
+            state.permit = true;
+            return;
+        };
+        state.notified.insert(id, true);
+        drop(state);
+
+        waker.wake();
+    }
+
+    /// Wakes every task currently waiting.
+    pub fn notify_waiters(&self) {
+        let mut state = self.state.lock().unwrap();
+        let mut wakers = Vec::new();
+        while let Some((id, waker)) = state.waiters.pop_front() {
+            state.notified.insert(id, false);
+            wakers.push(waker);
+        }
+        drop(state);
+
+        wakers.into_iter().for_each(Waker::wake);
+    }
+}
+
+impl Default for Notify {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub struct Notified<'a> {
+    notify: &'a Notify,
+    id: Option<u64>,
+}
+
+impl Future for Notified<'_> {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        coop::cooperative(cx, |cx| {
+            let notify = self.notify;
+            let mut state = notify.state.lock().unwrap();
+
+            match self.id {
+                None if state.permit => {
+                    state.permit = false;
+                    Poll::Ready(())
+                }
+                None => {
+                    self.id = Some(state.waiters.push((), cx.waker()));
+                    Poll::Pending
+                }
+                Some(id) if state.notified.remove(&id).is_some() => {
+                    self.id = None;
+                    Poll::Ready(())
+                }
+                Some(id) => {
+                    state.waiters.update(id, cx.waker());
+                    Poll::Pending
+                }
+            }
+        })
+    }
+}
+
+impl Drop for Notified<'_> {
+    fn drop(&mut self) {
+        let Some(id) = self.id else {
+            return;
+        };
+
+        let mut state = self.notify.state.lock().unwrap();
+        match state.notified.remove(&id) {
+            // Woken by `notify_one` but dropped before noticing; pass it on
+            Some(true) => {
+                drop(state);
+                self.notify.notify_one();
+            }
+            Some(false) => (),
+            None => {
+                state.waiters.remove(id);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::{FutureExt, task::noop_waker_ref};
+
+    use super::*;
+
+    fn poll<F: Future + Unpin>(future: &mut F) -> Poll<F::Output> {
+        future.poll_unpin(&mut Context::from_waker(noop_waker_ref()))
+    }
+
+    #[test]
+    fn notify_one_is_stored() {
+        let notify = Notify::new();
+        notify.notify_one();
+        assert!(poll(&mut notify.notified()).is_ready());
+        assert!(poll(&mut notify.notified()).is_pending());
+    }
+
+    #[test]
+    fn notify_one_wakes_oldest() {
+        let notify = Notify::new();
+        let mut first = notify.notified();
+        let mut second = notify.notified();
+        assert!(poll(&mut first).is_pending());
+        assert!(poll(&mut second).is_pending());
+
+        notify.notify_one();
+        assert!(poll(&mut second).is_pending());
+        assert!(poll(&mut first).is_ready());
+    }
+
+    #[test]
+    fn dropped_notified_passes_notification_on() {
+        let notify = Notify::new();
+        let mut first = notify.notified();
+        let mut second = notify.notified();
+        assert!(poll(&mut first).is_pending());
+        assert!(poll(&mut second).is_pending());
+
+        notify.notify_one();
+        drop(first);
+        assert!(poll(&mut second).is_ready());
+    }
+
+    #[test]
+    fn notify_waiters_is_not_stored() {
+        let notify = Notify::new();
+        let mut waiting = notify.notified();
+        assert!(poll(&mut waiting).is_pending());
+
+        notify.notify_waiters();
+        assert!(poll(&mut waiting).is_ready());
+        assert!(poll(&mut notify.notified()).is_pending());
+    }
+}
@@ -0,0 +1,298 @@
+use std::{
+    collections::HashSet,
+    fmt,
+    pin::Pin,
+    sync::Mutex,
+    task::{Context, Poll, Waker},
+};
+
+use super::WaitList;
+use crate::coop;
+
+/// Returned when acquiring from a closed semaphore.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AcquireError;
+
+impl fmt::Display for AcquireError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "semaphore closed")
+    }
+}
+
+impl std::error::Error for AcquireError {}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TryAcquireError {
+    Closed,
+    NoPermits,
+}
+
+impl fmt::Display for TryAcquireError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TryAcquireError::Closed => write!(f, "semaphore closed"),
+            TryAcquireError::NoPermits => write!(f, "no permits available"),
+        }
+    }
+}
+
+impl std::error::Error for TryAcquireError {}
+
+struct State {
+    permits: usize,
+    closed: bool,
+    // Permits each waiter asked for
+    waiters: WaitList<usize>,
+    // Waiters whose permits were handed over, but who have not seen it yet
+    granted: HashSet<u64>,
+}
+
+impl State {
+    // Hands permits to waiters in order, stopping at the first that cannot
+    // be satisfied so that larger requests are not starved by smaller ones
+    fn grant(&mut self) -> Vec<Waker> {
+        let mut wakers = Vec::new();
+        while let Some(&needed) = self.waiters.front()
+            && needed <= self.permits
+        {
+            self.permits -= needed;
+            let (id, waker) = self.waiters.pop_front().unwrap();
+            self.granted.insert(id);
+            wakers.push(waker);
+        }
+        wakers
+    }
+}
+
+/// A counting semaphore. Permits are handed out in the order they were
+/// asked for.
+pub struct Semaphore {
+    state: Mutex<State>,
+}
+
+impl Semaphore {
+    pub fn new(permits: usize) -> Self {
+        Semaphore {
+            state: Mutex::new(State {
+                permits,
+                closed: false,
+                waiters: WaitList::new(),
+                granted: HashSet::new(),
+            }),
+        }
+    }
+
+    pub fn available_permits(&self) -> usize {
+        self.state.lock().unwrap().permits
+    }
+
+    pub fn add_permits(&self, permits: usize) {
+        let mut state = self.state.lock().unwrap();
+        state.permits += permits;
+        let wakers = state.grant();
+        drop(state);
+
+        wakers.into_iter().for_each(Waker::wake);
+    }
+
+    /// Fails every pending and future acquire. Permits already handed out
+    /// stay valid.
+    pub fn close(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.closed = true;
+        let mut wakers = Vec::new();
+        while let Some((_, waker)) = state.waiters.pop_front() {
+            wakers.push(waker);
+        }
+        drop(state);
+
+        wakers.into_iter().for_each(Waker::wake);
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.state.lock().unwrap().closed
+    }
+
+    pub fn acquire(&self) -> Acquire<'_> {
+        self.acquire_many(1)
+    }
+
+    pub fn acquire_many(&self, permits: usize) -> Acquire<'_> {
+        Acquire {
+            semaphore: self,
+            permits,
+            id: None,
+        }
+    }
+
+    pub fn try_acquire(&self) -> Result<SemaphorePermit<'_>, TryAcquireError> {
+        self.try_acquire_many(1)
+    }
+
+    /// Takes permits only if nobody is already waiting for some.
+    pub fn try_acquire_many(&self, permits: usize) -> Result<SemaphorePermit<'_>, TryAcquireError> {
+        let mut state = self.state.lock().unwrap();
+        if state.closed {
+            Err(TryAcquireError::Closed)
+        } else if state.waiters.is_empty() && state.permits >= permits {
+            state.permits -= permits;
+            Ok(SemaphorePermit {
+                semaphore: self,
+                permits,
+            })
+        } else {
+            Err(TryAcquireError::NoPermits)
+        }
+    }
+}
+
+/// Permits taken from a semaphore; they are given back when dropped.
+#[must_use]
+pub struct SemaphorePermit<'a> {
+    semaphore: &'a Semaphore,
+    permits: usize,
+}
+
+impl SemaphorePermit<'_> {
+    /// Keeps the permits out of the semaphore for good.
+    pub fn forget(mut self) {
+        self.permits = 0;
+    }
+}
+
+impl Drop for SemaphorePermit<'_> {
+    fn drop(&mut self) {
+        if self.permits > 0 {
+            self.semaphore.add_permits(self.permits);
+        }
+    }
+}
+
+pub struct Acquire<'a> {
+    semaphore: &'a Semaphore,
+    permits: usize,
+    // Our place in the wait list, once we had to wait
+    id: Option<u64>,
+}
+
+impl<'a> Future for Acquire<'a> {
+    type Output = Result<SemaphorePermit<'a>, AcquireError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        coop::cooperative(cx, |cx| {
+            let (semaphore, permits) = (self.semaphore, self.permits);
+            let mut state = semaphore.state.lock().unwrap();
+
+            match self.id {
+                None if state.closed => Poll::Ready(Err(AcquireError)),
+                None if state.waiters.is_empty() && state.permits >= permits => {
+                    state.permits -= permits;
+                    Poll::Ready(Ok(SemaphorePermit { semaphore, permits }))
+                }
+                None => {
+                    self.id = Some(state.waiters.push(permits, cx.waker()));
+                    Poll::Pending
+                }
+                Some(id) if state.granted.remove(&id) => {
+                    self.id = None;
+                    Poll::Ready(Ok(SemaphorePermit { semaphore, permits }))
+                }
+                Some(_) if state.closed => {
+                    // Closing emptied the wait list
+                    self.id = None;
+                    Poll::Ready(Err(AcquireError))
+                }
+                Some(id) => {
+                    state.waiters.update(id, cx.waker());
+                    Poll::Pending
+                }
+            }
+        })
+    }
+}
+
+impl Drop for Acquire<'_> {
+    fn drop(&mut self) {
+        let Some(id) = self.id else {
+            return;
+        };
+
+        let mut state = self.semaphore.state.lock().unwrap();
+        if state.granted.remove(&id) {
+            // Granted but never picked up: pass the permits on
+            state.permits += self.permits;
+        } else {
+            state.waiters.remove(id);
+        }
+        // Either way, the waiters behind us may now be satisfiable
+        let wakers = state.grant();
+        drop(state);
+
+        wakers.into_iter().for_each(Waker::wake);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::{FutureExt, task::noop_waker_ref};
+
+    use super::*;
+
+    fn poll<F: Future + Unpin>(future: &mut F) -> Poll<F::Output> {
+        future.poll_unpin(&mut Context::from_waker(noop_waker_ref()))
+    }
+
+    #[test]
+    fn acquire_waits_for_release() {
+        let semaphore = Semaphore::new(1);
+        let permit = semaphore.try_acquire().unwrap();
+        let mut acquire = semaphore.acquire();
+        assert!(poll(&mut acquire).is_pending());
+
+        drop(permit);
+        assert!(matches!(poll(&mut acquire), Poll::Ready(Ok(_))));
+    }
+
+    #[test]
+    fn dropped_grant_passes_permits_on() {
+        let semaphore = Semaphore::new(1);
+        let permit = semaphore.try_acquire().unwrap();
+        let mut first = semaphore.acquire();
+        let mut second = semaphore.acquire();
+        assert!(poll(&mut first).is_pending());
+        assert!(poll(&mut second).is_pending());
+
+        // The permit goes to `first`, which never picks it up
+        drop(permit);
+        drop(first);
+        assert!(matches!(poll(&mut second), Poll::Ready(Ok(_))));
+    }
+
+    #[test]
+    fn large_request_is_not_starved() {
+        let semaphore = Semaphore::new(1);
+        let permit = semaphore.try_acquire().unwrap();
+        let mut large = semaphore.acquire_many(2);
+        assert!(poll(&mut large).is_pending());
+
+        semaphore.add_permits(1);
+        // Someone is already waiting, so newcomers queue behind them
+        assert_eq!(
+            semaphore.try_acquire().err(),
+            Some(TryAcquireError::NoPermits)
+        );
+        drop(permit);
+        assert!(matches!(poll(&mut large), Poll::Ready(Ok(_))));
+    }
+
+    #[test]
+    fn close_fails_waiters() {
+        let semaphore = Semaphore::new(0);
+        let mut acquire = semaphore.acquire();
+        assert!(poll(&mut acquire).is_pending());
+
+        semaphore.close();
+        assert!(matches!(poll(&mut acquire), Poll::Ready(Err(AcquireError))));
+        assert_eq!(semaphore.try_acquire().err(), Some(TryAcquireError::Closed));
+    }
+}
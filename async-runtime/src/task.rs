@@ -1,25 +1,748 @@
-use std::sync::{Arc, Mutex, mpsc::SyncSender};
+use std::{
+    any::Any,
+    cell::RefCell,
+    collections::HashMap,
+    fmt,
+    pin::Pin,
+    sync::{
+        Arc, Mutex, Weak,
+        atomic::{AtomicBool, AtomicU8, AtomicU64, AtomicUsize, Ordering},
+    },
+    task::{Context, Poll, Waker},
+    thread,
+    time::Instant,
+};
 
-use futures::future::{BoxFuture, FutureExt};
+use futures::{
+    future::{BoxFuture, FutureExt},
+    task::ArcWake,
+};
+use serde::Serialize;
+
+use crate::coop::{self, LongPollPolicy};
+use crate::metrics::{Metrics, MetricsHandle, TaskCounters, TaskStatus};
+use crate::power::PowerModel;
+use crate::task_local::TaskLocals;
+
+static NEXT_TASK_ID: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct TaskId(u64);
+
+impl TaskId {
+    pub(crate) fn next() -> Self {
+        TaskId(NEXT_TASK_ID.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+impl fmt::Display for TaskId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "task#{}", self.0)
+    }
+}
+
+/// Implemented by each executor to push runnable tasks onto its queue(s).
+pub(crate) trait RunQueue: Send + Sync {
+    // Never called twice for the same task without it being popped in
+    // between. Cancelled tasks may still sit in the queue until popped, so
+    // a bounded queue can fill up; it then drops the wake with
+    // `Task::wake_dropped`.
+    fn push(&self, task: Arc<Task>);
+
+    // Maximum number of live tasks the queue can hold, if bounded
+    fn capacity(&self) -> Option<usize> {
+        None
+    }
+
+    // Called once no spawner and no unfinished task is left; the executor
+    // should stop once its queue is drained, and later pushes are dropped
+    fn close(&self);
+}
+
+/// Returned by `Spawner::try_spawn` when the task cannot be spawned.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SpawnError {
+    /// The executor already has as many live tasks as its queue can hold.
+    Full { capacity: usize },
+}
+
+impl fmt::Display for SpawnError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SpawnError::Full { capacity } => {
+                write!(f, "executor is full: {} tasks are already live", capacity)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SpawnError {}
+
+/// What a task panicked with.
+pub type PanicPayload = Box<dyn Any + Send + 'static>;
+
+/// Called by the executor whenever a task panics, before its join handle
+/// is resolved.
+pub type PanicHook = Arc<dyn Fn(TaskId, &PanicPayload) + Send + Sync>;
+
+/// Best-effort panic message, for `panic!` with a string or format args.
+pub fn panic_message(payload: &PanicPayload) -> &str {
+    if let Some(message) = payload.downcast_ref::<&'static str>() {
+        message
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message
+    } else {
+        "Box<dyn Any>"
+    }
+}
+
+pub(crate) fn default_panic_hook(id: TaskId, payload: &PanicPayload) {
+    eprintln!(
+        "{} panicked: {}; executor keeps running",
+        id,
+        panic_message(payload)
+    );
+}
+
+/// State shared by one executor's spawners and tasks.
+pub(crate) struct Scheduler {
+    run_queue: Box<dyn RunQueue>,
+
+    // Spawner handles plus unfinished tasks
+    refs: AtomicUsize,
+    // Unfinished tasks, so stragglers can be cancelled at shutdown
+    tasks: Mutex<HashMap<TaskId, Weak<Task>>>,
+
+    panic_hook: Mutex<PanicHook>,
+    pub(crate) long_poll_policy: Mutex<LongPollPolicy>,
+
+    pub(crate) metrics: Arc<Metrics>,
+}
+
+impl Scheduler {
+    pub(crate) fn new(run_queue: impl RunQueue + 'static, metrics: Arc<Metrics>) -> Arc<Self> {
+        Arc::new(Scheduler {
+            run_queue: Box::new(run_queue),
+            refs: AtomicUsize::new(0),
+            tasks: Mutex::new(HashMap::new()),
+            panic_hook: Mutex::new(Arc::new(default_panic_hook)),
+            long_poll_policy: Mutex::new(LongPollPolicy::default()),
+            metrics,
+        })
+    }
+
+    pub(crate) fn metrics(&self) -> MetricsHandle {
+        MetricsHandle {
+            metrics: self.metrics.clone(),
+        }
+    }
+
+    pub(crate) fn set_panic_hook(&self, hook: PanicHook) {
+        *self.panic_hook.lock().unwrap() = hook;
+    }
+
+    fn acquire(&self) {
+        self.refs.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn release(&self) {
+        if self.refs.fetch_sub(1, Ordering::AcqRel) == 1 {
+            self.run_queue.close();
+        }
+    }
+
+    pub(crate) fn schedule(&self, task: Arc<Task>) {
+        self.run_queue.push(task);
+    }
+
+    pub(crate) fn close(&self) {
+        self.run_queue.close();
+    }
+
+    fn task_finished(&self, id: TaskId, status: TaskStatus) {
+        self.metrics.task_finished(id, status);
+        self.tasks.lock().unwrap().remove(&id);
+        self.release();
+    }
+
+    /// Number of spawned tasks that have not finished yet.
+    pub(crate) fn live_tasks(&self) -> usize {
+        self.tasks.lock().unwrap().len()
+    }
+
+    /// Drops the future of every unfinished task; their joiners see
+    /// `JoinError::Cancelled`.
+    pub(crate) fn cancel_all(&self) {
+        // Collect first: dropping a future may spawn or finish other tasks
+        let tasks: Vec<_> = self.tasks.lock().unwrap().values().cloned().collect();
+        for task in tasks.iter().filter_map(Weak::upgrade) {
+            task.cancel();
+        }
+    }
+}
+
+// Task scheduling states
+// Waiting for a wake
+const IDLE: u8 = 0;
+// In the run queue
+const SCHEDULED: u8 = 1;
+// Being polled by the executor
+const RUNNING: u8 = 2;
+// Woken while being polled; requeued once the poll returns
+const NOTIFIED: u8 = 3;
+// Finished or cancelled; wakes are ignored
+const COMPLETE: u8 = 4;
+// Panicked while being polled; wakes are ignored
+const FAILED: u8 = 5;
+
+// What an `AbortHandle` controls, without knowing the task's output type or
+// which executor runs it
+pub(crate) trait Abortable: Send + Sync {
+    fn abort(self: Arc<Self>);
+
+    fn is_finished(&self) -> bool;
+}
+
+impl Abortable for Task {
+    fn abort(self: Arc<Self>) {
+        self.aborted.store(true, Ordering::Release);
+        // Get the task to its next scheduling point
+        ArcWake::wake_by_ref(&self);
+    }
+
+    fn is_finished(&self) -> bool {
+        self.state.load(Ordering::Acquire) >= COMPLETE
+    }
+}
+
+// Lets the executor resolve a task's join handle with a panic without
+// knowing its output type
+pub(crate) trait PanicSink {
+    fn panicked(&self, payload: PanicPayload);
+}
 
 pub struct Task {
+    pub(crate) id: TaskId,
     pub future: Mutex<Option<BoxFuture<'static, ()>>>,
 
-    pub sender: SyncSender<Arc<Task>>,
+    state: AtomicU8,
+    // Set by `AbortHandle::abort`; the executor drops the future instead of polling it
+    aborted: AtomicBool,
+    // Used to order the ready queue under earliest-deadline-first scheduling
+    deadline: Mutex<Option<Instant>>,
+    pub(crate) scheduler: Arc<Scheduler>,
+    // Values of `task_local!` keys for this task
+    pub(crate) locals: Mutex<TaskLocals>,
+    // Poll and wake counts, shared with the executor's metrics
+    pub(crate) counters: Arc<TaskCounters>,
+
+    join_sink: Arc<dyn PanicSink + Send + Sync>,
+}
+
+thread_local! {
+    // The task being polled on this thread, if any
+    static CURRENT_TASK: RefCell<Option<Arc<Task>>> = const { RefCell::new(None) };
+}
+
+/// Restores the previously current task when dropped.
+pub(crate) struct EnterGuard {
+    previous: Option<Arc<Task>>,
+}
+
+impl Drop for EnterGuard {
+    fn drop(&mut self) {
+        CURRENT_TASK.with(|current| *current.borrow_mut() = self.previous.take());
+    }
+}
+
+/// Runs `f` with the task currently being polled on this thread; `None`
+/// when called outside of a task.
+pub(crate) fn with_current<R>(f: impl FnOnce(&Arc<Task>) -> R) -> Option<R> {
+    let task = CURRENT_TASK.with(|current| current.borrow().clone())?;
+    Some(f(&task))
+}
+
+/// The id of the task currently being polled on this thread.
+pub fn current_task_id() -> Option<TaskId> {
+    with_current(|task| task.id)
+}
+
+impl ArcWake for Task {
+    fn wake_by_ref(arc_self: &Arc<Self>) {
+        arc_self.counters.woken();
+
+        // Coalesce wakes: only the IDLE -> SCHEDULED transition enqueues
+        let mut state = arc_self.state.load(Ordering::Acquire);
+        loop {
+            let next = match state {
+                IDLE => SCHEDULED,
+                RUNNING => NOTIFIED,
+                _ => return,
+            };
+            match arc_self.state.compare_exchange_weak(
+                state,
+                next,
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            ) {
+                Ok(_) if next == SCHEDULED => break,
+                Ok(_) => return,
+                Err(actual) => state = actual,
+            }
+        }
+
+        arc_self.scheduler.schedule(arc_self.clone());
+    }
+}
+
+impl Task {
+    pub fn id(&self) -> TaskId {
+        self.id
+    }
+
+    /// Makes this the current task until the guard is dropped.
+    pub(crate) fn enter(self: &Arc<Self>) -> EnterGuard {
+        let previous = CURRENT_TASK.with(|current| current.replace(Some(self.clone())));
+        EnterGuard { previous }
+    }
+
+    pub fn deadline(&self) -> Option<Instant> {
+        *self.deadline.lock().unwrap()
+    }
+
+    pub(crate) fn set_deadline(&self, deadline: Option<Instant>) {
+        *self.deadline.lock().unwrap() = deadline;
+    }
+
+    // Called by the executor with the future locked, before polling.
+    // Returns false if the task completed (e.g. was cancelled) while queued.
+    pub(crate) fn start_running(&self) -> bool {
+        self.state
+            .compare_exchange(SCHEDULED, RUNNING, Ordering::AcqRel, Ordering::Acquire)
+            .is_ok()
+    }
+
+    // Called by the executor with the future locked, after a pending poll.
+    // Returns true if the task was woken during the poll and must be requeued.
+    pub(crate) fn stop_running(&self) -> bool {
+        match self
+            .state
+            .compare_exchange(RUNNING, IDLE, Ordering::AcqRel, Ordering::Acquire)
+        {
+            Ok(_) => false,
+            Err(_) => {
+                self.state.store(SCHEDULED, Ordering::Release);
+                true
+            }
+        }
+    }
+
+    pub(crate) fn is_aborted(&self) -> bool {
+        self.aborted.load(Ordering::Acquire)
+    }
+
+    /// Must be called exactly once, after the future was taken out for good
+    /// (completed, or dropped on abort).
+    pub(crate) fn finish(&self, status: TaskStatus) {
+        self.state.store(COMPLETE, Ordering::Release);
+        self.scheduler.task_finished(self.id, status);
+    }
+
+    /// Like `finish`, for a task whose poll panicked. `future` must be the
+    /// task's (already taken) future; it is dropped only after the join
+    /// handle has been resolved with the payload.
+    pub(crate) fn fail(&self, future: BoxFuture<'static, ()>, payload: PanicPayload) {
+        self.state.store(FAILED, Ordering::Release);
+
+        let hook = self.scheduler.panic_hook.lock().unwrap().clone();
+        hook(self.id, &payload);
+        self.join_sink.panicked(payload);
+
+        drop(future);
+        self.scheduler.task_finished(self.id, TaskStatus::Panicked);
+    }
+
+    // Called by a run queue that had no room for the task. The task goes
+    // back to waiting, so a later wake can enqueue it again.
+    pub(crate) fn wake_dropped(&self) {
+        self.scheduler.metrics.wake_dropped();
+        let _ = self
+            .state
+            .compare_exchange(SCHEDULED, IDLE, Ordering::AcqRel, Ordering::Acquire);
+    }
+
+    fn cancel(&self) {
+        let mut future_slot = self.future.lock().unwrap();
+        if let Some(future) = future_slot.take() {
+            self.state.store(COMPLETE, Ordering::Release);
+            drop(future_slot);
+            drop(future);
+            self.scheduler.task_finished(self.id, TaskStatus::Cancelled);
+        }
+    }
 }
 
 pub struct Spawner {
-    pub sender: SyncSender<Arc<Task>>,
+    pub(crate) scheduler: Arc<Scheduler>,
+}
+
+impl Clone for Spawner {
+    fn clone(&self) -> Self {
+        Spawner::new(self.scheduler.clone())
+    }
+}
+
+impl Drop for Spawner {
+    fn drop(&mut self) {
+        self.scheduler.release();
+    }
 }
 
 impl Spawner {
-    pub fn spawn(&self, future: impl Future<Output = ()> + 'static + Send) {
-        let future = future.boxed();
-        let task = Task {
+    pub(crate) fn new(scheduler: Arc<Scheduler>) -> Self {
+        scheduler.acquire();
+        Spawner { scheduler }
+    }
+
+    /// Spawns a task.
+    ///
+    /// # Panics
+    ///
+    /// If the executor is full; see `try_spawn`.
+    pub fn spawn<T>(&self, future: impl Future<Output = T> + 'static + Send) -> JoinHandle<T>
+    where
+        T: Send + 'static,
+    {
+        self.spawn_task(future, None)
+            .unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    /// Spawns a task, unless the executor already has as many live tasks
+    /// as it can hold.
+    pub fn try_spawn<T>(
+        &self,
+        future: impl Future<Output = T> + 'static + Send,
+    ) -> Result<JoinHandle<T>, SpawnError>
+    where
+        T: Send + 'static,
+    {
+        self.spawn_task(future, None)
+    }
+
+    /// Spawns a task that an earliest-deadline-first executor runs ahead of
+    /// tasks with later (or no) deadlines.
+    ///
+    /// # Panics
+    ///
+    /// If the executor is full; see `try_spawn`.
+    pub fn spawn_with_deadline<T>(
+        &self,
+        deadline: Instant,
+        future: impl Future<Output = T> + 'static + Send,
+    ) -> JoinHandle<T>
+    where
+        T: Send + 'static,
+    {
+        self.spawn_task(future, Some(deadline))
+            .unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    fn spawn_task<T>(
+        &self,
+        future: impl Future<Output = T> + 'static + Send,
+        deadline: Option<Instant>,
+    ) -> Result<JoinHandle<T>, SpawnError>
+    where
+        T: Send + 'static,
+    {
+        let id = TaskId::next();
+        let (notifier, mut handle) = join_pair(id);
+        let future = async move {
+            let output = future.await;
+            notifier.complete(Ok(output));
+        }
+        .boxed();
+        let task = Arc::new(Task {
+            id,
             future: Mutex::new(Some(future)),
-            sender: self.sender.clone(),
-        };
+            state: AtomicU8::new(SCHEDULED),
+            aborted: AtomicBool::new(false),
+            deadline: Mutex::new(deadline),
+            scheduler: self.scheduler.clone(),
+            locals: Mutex::new(TaskLocals::new()),
+            counters: Arc::new(TaskCounters::default()),
+            join_sink: handle.shared_state.clone(),
+        });
+        handle.task = Arc::<Task>::downgrade(&task);
+
+        {
+            // Checked and inserted under one lock, so racing spawners cannot
+            // both take the last free slot
+            let mut tasks = self.scheduler.tasks.lock().unwrap();
+            if let Some(capacity) = self.scheduler.run_queue.capacity()
+                && tasks.len() >= capacity
+            {
+                return Err(SpawnError::Full { capacity });
+            }
+            self.scheduler.acquire();
+            self.scheduler
+                .metrics
+                .task_spawned(task.id, task.counters.clone());
+            tasks.insert(task.id, Arc::downgrade(&task));
+        }
+        self.scheduler.schedule(task);
+
+        Ok(handle)
+    }
+
+    /// Number of tasks spawned on this executor that have not finished yet.
+    pub fn live_tasks(&self) -> usize {
+        self.scheduler.live_tasks()
+    }
+
+    pub fn metrics(&self) -> MetricsHandle {
+        self.scheduler.metrics()
+    }
+
+    /// Replaces the hook called whenever a task on this executor panics.
+    /// The default one logs the task and panic message to stderr.
+    pub fn set_panic_hook(&self, hook: impl Fn(TaskId, &PanicPayload) + Send + Sync + 'static) {
+        self.scheduler.set_panic_hook(Arc::new(hook));
+    }
+
+    /// Sets how the executor reacts to polls that block it for too long.
+    pub fn set_long_poll_policy(&self, policy: LongPollPolicy) {
+        *self.scheduler.long_poll_policy.lock().unwrap() = policy;
+    }
+
+    /// Makes metrics snapshots include a CPU energy estimate.
+    pub fn set_power_model(&self, model: impl PowerModel + 'static) {
+        self.scheduler.metrics.set_power_model(Arc::new(model));
+    }
+}
+
+/// Why a task did not produce an output.
+#[derive(Debug)]
+pub enum JoinError {
+    /// The task's future was dropped before it completed.
+    Cancelled,
+    /// The task panicked while being polled.
+    Panicked(PanicPayload),
+}
+
+impl JoinError {
+    pub fn is_cancelled(&self) -> bool {
+        matches!(self, JoinError::Cancelled)
+    }
+
+    pub fn is_panic(&self) -> bool {
+        matches!(self, JoinError::Panicked(_))
+    }
+
+    /// The panic payload, e.g. to `std::panic::resume_unwind` it.
+    pub fn try_into_panic(self) -> Result<PanicPayload, JoinError> {
+        match self {
+            JoinError::Panicked(payload) => Ok(payload),
+            error => Err(error),
+        }
+    }
+}
+
+impl fmt::Display for JoinError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JoinError::Cancelled => write!(f, "task was cancelled"),
+            JoinError::Panicked(payload) => {
+                write!(f, "task panicked: {}", panic_message(payload))
+            }
+        }
+    }
+}
+
+impl std::error::Error for JoinError {}
+
+pub(crate) struct JoinState<T> {
+    result: Option<Result<T, JoinError>>,
+    waker: Option<Waker>,
+    consumed: bool,
+}
+
+/// Resolves to the output of a spawned task.
+pub struct JoinHandle<T> {
+    pub(crate) shared_state: Arc<Mutex<JoinState<T>>>,
+    pub(crate) task: Weak<dyn Abortable>,
+    id: TaskId,
+}
+
+impl<T> Future for JoinHandle<T> {
+    type Output = Result<T, JoinError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        coop::cooperative(cx, |cx| {
+            let mut shared_state = self.shared_state.lock().unwrap();
+            assert!(!shared_state.consumed, "JoinHandle polled after completion");
+
+            if let Some(result) = shared_state.result.take() {
+                shared_state.consumed = true;
+                shared_state.waker = None;
+                Poll::Ready(result)
+            } else {
+                shared_state.waker = Some(cx.waker().clone());
 
-        self.sender.try_send(Arc::new(task)).unwrap();
+                Poll::Pending
+            }
+        })
     }
 }
+
+impl<T> JoinHandle<T> {
+    pub fn id(&self) -> TaskId {
+        self.id
+    }
+
+    /// Whether the task has finished, successfully or not.
+    pub fn is_finished(&self) -> bool {
+        let shared_state = self.shared_state.lock().unwrap();
+        shared_state.consumed || shared_state.result.is_some()
+    }
+
+    /// Cancels the task; see `AbortHandle::abort`.
+    pub fn abort(&self) {
+        self.abort_handle().abort();
+    }
+
+    /// A handle that can cancel the task without awaiting its output.
+    pub fn abort_handle(&self) -> AbortHandle {
+        AbortHandle {
+            task: self.task.clone(),
+        }
+    }
+
+    /// Turns this handle into one that cancels the task when dropped, for
+    /// background work that must not outlive its owner.
+    pub fn abort_on_drop(self) -> AbortOnDropHandle<T> {
+        AbortOnDropHandle { join_handle: self }
+    }
+}
+
+/// Cancels a spawned task from anywhere.
+#[derive(Clone)]
+pub struct AbortHandle {
+    task: Weak<dyn Abortable>,
+}
+
+impl AbortHandle {
+    /// Drops the task's future the next time the executor picks the task
+    /// up, running its destructors; joiners see `JoinError::Cancelled`.
+    /// Does nothing if the task already finished, or if it is a blocking
+    /// job, which cannot be interrupted.
+    pub fn abort(&self) {
+        if let Some(task) = self.task.upgrade() {
+            task.abort();
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.task.upgrade().is_none_or(|task| task.is_finished())
+    }
+}
+
+/// A `JoinHandle` that aborts its task when dropped.
+pub struct AbortOnDropHandle<T> {
+    join_handle: JoinHandle<T>,
+}
+
+impl<T> AbortOnDropHandle<T> {
+    pub fn abort_handle(&self) -> AbortHandle {
+        self.join_handle.abort_handle()
+    }
+}
+
+impl<T> Future for AbortOnDropHandle<T> {
+    type Output = Result<T, JoinError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        Pin::new(&mut self.join_handle).poll(cx)
+    }
+}
+
+impl<T> Drop for AbortOnDropHandle<T> {
+    fn drop(&mut self) {
+        self.join_handle.abort();
+    }
+}
+
+// Sets the result unless one was already set, waking the joiner
+fn resolve<T>(shared_state: &Mutex<JoinState<T>>, result: Result<T, JoinError>) {
+    let waker = {
+        let mut shared_state = shared_state.lock().unwrap();
+        if shared_state.consumed || shared_state.result.is_some() {
+            return;
+        }
+        shared_state.result = Some(result);
+        shared_state.waker.take()
+    };
+    if let Some(waker) = waker {
+        waker.wake();
+    }
+}
+
+// A job on the blocking pool: it runs to the end once started, so abort
+// does nothing; it is finished once its result is in
+impl<T: Send> Abortable for Mutex<JoinState<T>> {
+    fn abort(self: Arc<Self>) {}
+
+    fn is_finished(&self) -> bool {
+        let shared_state = self.lock().unwrap();
+        shared_state.consumed || shared_state.result.is_some()
+    }
+}
+
+impl<T> PanicSink for Mutex<JoinState<T>> {
+    fn panicked(&self, payload: PanicPayload) {
+        resolve(self, Err(JoinError::Panicked(payload)));
+    }
+}
+
+/// The producing half of a `JoinHandle`. If it is dropped without
+/// completing (because the task's future was dropped), the handle resolves
+/// to an error instead of hanging.
+pub(crate) struct JoinNotifier<T> {
+    shared_state: Arc<Mutex<JoinState<T>>>,
+}
+
+impl<T> JoinNotifier<T> {
+    pub(crate) fn complete(self, result: Result<T, JoinError>) {
+        resolve(&self.shared_state, result);
+    }
+}
+
+impl<T> Drop for JoinNotifier<T> {
+    fn drop(&mut self) {
+        // A panicking task drops its state while unwinding; the executor
+        // resolves the handle with the payload once it catches the panic
+        if !thread::panicking() {
+            // No-op if the task already completed
+            resolve(&self.shared_state, Err(JoinError::Cancelled));
+        }
+    }
+}
+
+pub(crate) fn join_pair<T>(id: TaskId) -> (JoinNotifier<T>, JoinHandle<T>) {
+    let shared_state = Arc::new(Mutex::new(JoinState {
+        result: None,
+        waker: None,
+        consumed: false,
+    }));
+
+    (
+        JoinNotifier {
+            shared_state: shared_state.clone(),
+        },
+        JoinHandle {
+            shared_state,
+            task: Weak::<Task>::new(),
+            id,
+        },
+    )
+}
@@ -0,0 +1,118 @@
+use std::{any::Any, collections::HashMap, fmt, marker::PhantomData, sync::Arc};
+
+use crate::task::with_current;
+
+// A task's local values, keyed by the address of their `LocalKey`
+pub(crate) type TaskLocals = HashMap<usize, Arc<dyn Any + Send + Sync>>;
+
+/// Declares task-local keys: each task that runs on a `Task`-based executor
+/// (`Runtime`, `spawn_executor_thread`, the work-stealing pool) has its own
+/// value for the key, which follows the task across polls and threads.
+///
+/// ```ignore
+/// task_local! {
+///     pub static NODE_ID: u32;
+/// }
+///
+/// spawner.spawn(async {
+///     NODE_ID.set(7);
+///     some_deeply_nested_call().await; // can call NODE_ID.get()
+/// });
+/// ```
+#[macro_export]
+macro_rules! task_local {
+    ($($(#[$attr:meta])* $vis:vis static $name:ident: $t:ty;)+) => {
+        $(
+            $(#[$attr])*
+            $vis static $name: $crate::task_local::LocalKey<$t> =
+                $crate::task_local::LocalKey::new(stringify!($name));
+        )+
+    };
+}
+
+/// A key for a task-local value; declare one with `task_local!`.
+pub struct LocalKey<T> {
+    // Also keeps the static from being zero-sized, so its address is unique
+    name: &'static str,
+    _value: PhantomData<fn() -> T>,
+}
+
+/// Returned when a task-local value is read outside of a task, or before
+/// the current task has set it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AccessError {
+    name: &'static str,
+}
+
+impl fmt::Display for AccessError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "task-local {} is not set for the current task",
+            self.name
+        )
+    }
+}
+
+impl std::error::Error for AccessError {}
+
+impl<T: Send + Sync + 'static> LocalKey<T> {
+    #[doc(hidden)]
+    pub const fn new(name: &'static str) -> Self {
+        LocalKey {
+            name,
+            _value: PhantomData,
+        }
+    }
+
+    fn key(&'static self) -> usize {
+        self as *const Self as usize
+    }
+
+    /// Sets the current task's value, replacing any previous one.
+    ///
+    /// Panics when called outside of a task.
+    pub fn set(&'static self, value: T) {
+        with_current(|task| {
+            task.locals
+                .lock()
+                .unwrap()
+                .insert(self.key(), Arc::new(value));
+        })
+        .unwrap_or_else(|| panic!("task-local {} set outside of a task", self.name));
+    }
+
+    /// Removes the current task's value. Does nothing outside of a task.
+    pub fn clear(&'static self) {
+        with_current(|task| task.locals.lock().unwrap().remove(&self.key()));
+    }
+
+    /// Runs `f` with the current task's value.
+    pub fn try_with<R>(&'static self, f: impl FnOnce(&T) -> R) -> Result<R, AccessError> {
+        // Cloned out of the map, so `f` may use other task-locals
+        let value = with_current(|task| task.locals.lock().unwrap().get(&self.key()).cloned())
+            .flatten()
+            .ok_or(AccessError { name: self.name })?;
+        let value = value
+            .downcast_ref::<T>()
+            .expect("task-local keys are unique per static");
+        Ok(f(value))
+    }
+
+    /// Runs `f` with the current task's value.
+    ///
+    /// Panics when called outside of a task, or if the task has not set a value.
+    pub fn with<R>(&'static self, f: impl FnOnce(&T) -> R) -> R {
+        self.try_with(f).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    /// A copy of the current task's value.
+    ///
+    /// Panics when called outside of a task, or if the task has not set a value.
+    pub fn get(&'static self) -> T
+    where
+        T: Clone,
+    {
+        self.with(T::clone)
+    }
+}
@@ -1,96 +1,236 @@
 use std::{
+    convert::Infallible,
+    ops::Range,
     pin::Pin,
-    sync::{Arc, Mutex},
-    task::{Context, Poll, Waker},
-    thread,
+    task::{Context, Poll},
     time::Duration,
 };
 
-use rand::{Rng, SeedableRng, rngs::SmallRng};
+use crossbeam::channel::{Receiver, Sender, bounded};
+use futures::Stream;
+use rand::Rng;
 
-type Temperature = f64;
+use crate::karma::{Peripheral, PeripheralMsg};
+use crate::sensor::{self, PlaybackOptions, SampleBuffer, Sensor, Trace};
 
-struct SharedState {
-    future_exists: bool,
-    buffer: Vec<Temperature>,
-    waker: Option<Waker>,
+pub type Temperature = f64;
+
+const PERIOD: Range<Duration> = Duration::from_secs(1)..Duration::from_secs(5);
+// Outputs raised while none are taken are dropped beyond this many
+const OUTPUT_CAPACITY: usize = 64;
+
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub enum TemperatureState {
+    Off,
+    Configured,
+    Sampling,
 }
 
-pub struct TemperatureSensorFuture {
-    shared_state: Arc<Mutex<SharedState>>,
+/// Settings the sensor loses when its power drops.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct TemperatureConfig {
+    /// Calibration offset added to every reading, in °C
+    pub offset: f64,
+    /// Readings are rounded to a multiple of this, in °C; 0 keeps them as is
+    pub resolution: f64,
 }
 
-impl Future for TemperatureSensorFuture {
-    type Output = Vec<Temperature>;
+impl TemperatureConfig {
+    fn apply(&self, temp: Temperature) -> Temperature {
+        let temp = temp + self.offset;
+        if self.resolution > 0.0 {
+            (temp / self.resolution).round() * self.resolution
+        } else {
+            temp
+        }
+    }
+}
 
-    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        let mut shared_state = self.shared_state.lock().unwrap();
-        assert!(shared_state.future_exists);
+#[derive(Clone, Debug)]
+pub enum TemperatureInputMsg {
+    Configure(TemperatureConfig),
+    Start,
+    Stop,
+}
 
-        if shared_state.buffer.len() != 0 {
-            let buffer = shared_state.buffer.clone();
-            shared_state.buffer.clear();
-            shared_state.waker = None;
-            shared_state.future_exists = false;
-            Poll::Ready(buffer)
-        } else {
-            shared_state.waker = Some(cx.waker().clone());
+#[derive(Clone, Debug)]
+pub enum TemperatureOutputMsg {
+    // Raised for each sample read while sampling
+    Sample(Temperature),
+}
+
+impl PeripheralMsg<TemperatureState> for TemperatureInputMsg {
+    fn required_initial_state(&self) -> TemperatureState {
+        match self {
+            TemperatureInputMsg::Configure(_) => TemperatureState::Off,
+            TemperatureInputMsg::Start => TemperatureState::Configured,
+            TemperatureInputMsg::Stop => TemperatureState::Sampling,
+        }
+    }
 
-            Poll::Pending
+    fn resulting_state(&self) -> TemperatureState {
+        match self {
+            TemperatureInputMsg::Configure(_) => TemperatureState::Configured,
+            TemperatureInputMsg::Start => TemperatureState::Sampling,
+            TemperatureInputMsg::Stop => TemperatureState::Configured,
         }
     }
 }
 
+impl PeripheralMsg<TemperatureState> for TemperatureOutputMsg {
+    fn required_initial_state(&self) -> TemperatureState {
+        match self {
+            TemperatureOutputMsg::Sample(_) => TemperatureState::Sampling,
+        }
+    }
+
+    fn resulting_state(&self) -> TemperatureState {
+        match self {
+            TemperatureOutputMsg::Sample(_) => TemperatureState::Sampling,
+        }
+    }
+}
+
+/// A simulated temperature sensor, in °C. It keeps its samples only while
+/// powered and sampling; managed by a `Karma`, its configuration is
+/// restored after a power cycle by replaying the support queue.
 pub struct TemperatureSensor {
-    shared_state: Arc<Mutex<SharedState>>,
+    id: u64,
+    state: TemperatureState,
+    config: Option<TemperatureConfig>,
+    buffer: SampleBuffer<Temperature>,
+    period: Duration,
+
+    // Interrupt queue for the outputs raised by the sensor
+    output_sender: Sender<TemperatureOutputMsg>,
+    output_receiver: Receiver<TemperatureOutputMsg>,
 }
 
 impl TemperatureSensor {
-    pub fn read(&mut self) -> Option<TemperatureSensorFuture> {
-        let mut handle = self.shared_state.lock().unwrap();
+    /// A sensor already configured with the defaults and sampling.
+    pub fn new() -> Self {
+        let buffer = sensor::simulate(PERIOD, |rng| rng.random_range(0.0..100.0));
+
+        Self::sampling(buffer, sensor::mean(&PERIOD))
+    }
 
-        if handle.future_exists {
-            return None;
+    /// A sensor that is off until configured and started, typically
+    /// through a `Karma`.
+    pub fn off(id: u64) -> Self {
+        let buffer = sensor::simulate(PERIOD, |rng| rng.random_range(0.0..100.0));
+        buffer.set_enabled(false);
+        let (output_sender, output_receiver) = bounded(OUTPUT_CAPACITY);
+
+        Self {
+            id,
+            state: TemperatureState::Off,
+            config: None,
+            buffer,
+            period: sensor::mean(&PERIOD),
+            output_sender,
+            output_receiver,
         }
+    }
 
-        handle.future_exists = true;
-        assert!(handle.waker.is_none());
+    /// A sensor replaying recorded temperatures, configured with the
+    /// defaults and sampling. Unless looped, its stream ends with the
+    /// trace. A single-sample trace reports a zero sampling period.
+    pub fn from_trace(trace: Trace<Temperature>, options: PlaybackOptions) -> Self {
+        let interval = trace.mean_interval().unwrap_or_default();
+        let buffer = trace.play(options);
 
-        Some(TemperatureSensorFuture {
-            shared_state: self.shared_state.clone(),
-        })
+        Self::sampling(buffer, interval.div_f64(options.speed))
     }
 
-    pub fn new() -> Self {
-        let shared_state = Arc::new(Mutex::new(SharedState {
-            future_exists: false,
-            buffer: vec![],
-            waker: None,
-        }));
-
-        let thread_shared_state = shared_state.clone();
-        thread::spawn(move || {
-            let mut rng = SmallRng::from_os_rng();
-
-            loop {
-                let duration = Duration::from_secs(rng.random_range(1..5));
-
-                thread::sleep(duration);
-
-                let temp: f64 = rng.random_range(0.0..100.0);
-                // send the temperature
-                let mut handle = thread_shared_state.lock().unwrap();
-                handle.buffer.push(temp);
-
-                // call the waker
-                if let Some(waker) = &handle.waker {
-                    waker.wake_by_ref();
-                }
-            }
-        });
+    fn sampling(buffer: SampleBuffer<Temperature>, period: Duration) -> Self {
+        let (output_sender, output_receiver) = bounded(OUTPUT_CAPACITY);
 
         Self {
-            shared_state,
+            id: 0,
+            state: TemperatureState::Sampling,
+            config: Some(TemperatureConfig::default()),
+            buffer,
+            period,
+            output_sender,
+            output_receiver,
         }
     }
+
+    /// The outputs the sensor raises, in order: a `Sample` for each
+    /// sample read. Outputs not taken are lost on a power cycle.
+    pub fn outputs(&self) -> Receiver<TemperatureOutputMsg> {
+        self.output_receiver.clone()
+    }
+}
+
+impl Peripheral<TemperatureState> for TemperatureSensor {
+    type InputMsg = TemperatureInputMsg;
+    type OutputMsg = TemperatureOutputMsg;
+    type Error = Infallible;
+
+    fn get_id(&self) -> u64 {
+        self.id
+    }
+
+    fn get_current_state(&self) -> TemperatureState {
+        self.state
+    }
+
+    fn send_input(&mut self, msg: TemperatureInputMsg) -> Result<(), Infallible> {
+        match msg {
+            TemperatureInputMsg::Configure(config) => self.config = Some(config),
+            TemperatureInputMsg::Start => self.buffer.set_enabled(true),
+            TemperatureInputMsg::Stop => self.buffer.set_enabled(false),
+        }
+        self.state = msg.resulting_state();
+        Ok(())
+    }
+
+    fn power_cycle(&mut self) {
+        // Samples and outputs not read yet and the configuration are lost
+        self.buffer.set_enabled(false);
+        self.buffer.clear();
+        while self.output_receiver.try_recv().is_ok() {}
+        self.config = None;
+        self.state = TemperatureState::Off;
+    }
+}
+
+impl Sensor for TemperatureSensor {
+    fn units(&self) -> &'static str {
+        "°C"
+    }
+
+    fn sampling_period(&self) -> Duration {
+        self.period
+    }
+
+    fn poll_read(&mut self, cx: &mut Context<'_>, max: usize) -> Poll<Vec<Temperature>> {
+        let config = self.config.unwrap_or_default();
+        let temps = self.buffer.poll_take(cx, max);
+        temps.map(|temps| {
+            let temps: Vec<_> = temps.into_iter().map(|temp| config.apply(temp)).collect();
+            for &temp in &temps {
+                // Dropped if nobody keeps up with the outputs
+                let _ = self
+                    .output_sender
+                    .try_send(TemperatureOutputMsg::Sample(temp));
+            }
+            temps
+        })
+    }
+}
+
+impl Stream for TemperatureSensor {
+    type Item = Temperature;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.poll_read(cx, 1).map(|mut temps| temps.pop())
+    }
+}
+
+impl Default for TemperatureSensor {
+    fn default() -> Self {
+        Self::new()
+    }
 }
@@ -0,0 +1,183 @@
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use futures::{Stream, StreamExt};
+
+use super::TimerFuture;
+use crate::clock;
+
+/// What an `Interval` does when ticks were missed because the consumer
+/// fell behind by more than one period.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MissedTickBehavior {
+    /// Yield the missed ticks back to back until caught up, keeping the
+    /// original schedule.
+    #[default]
+    Burst,
+    /// Tick once now, and schedule later ticks a full period from now.
+    Delay,
+    /// Tick once now, and drop the missed ticks, keeping to the original
+    /// schedule from then on.
+    Skip,
+}
+
+/// Ticks every `period`, the first tick completing immediately.
+pub fn interval(period: Duration) -> Interval {
+    interval_at(clock::now(), period)
+}
+
+/// Ticks every `period`, starting at `start`.
+pub fn interval_at(start: Instant, period: Duration) -> Interval {
+    assert!(!period.is_zero(), "an interval's period must be non-zero");
+
+    Interval {
+        period,
+        next: start,
+        timer: None,
+        missed_tick_behavior: MissedTickBehavior::default(),
+    }
+}
+
+/// A stream of periodic ticks, each yielding the time it was scheduled for.
+pub struct Interval {
+    period: Duration,
+    // When the next tick is due
+    next: Instant,
+    timer: Option<TimerFuture>,
+    missed_tick_behavior: MissedTickBehavior,
+}
+
+impl Interval {
+    /// Waits for the next tick.
+    pub async fn tick(&mut self) -> Instant {
+        self.next().await.expect("intervals never end")
+    }
+
+    pub fn period(&self) -> Duration {
+        self.period
+    }
+
+    pub fn set_missed_tick_behavior(&mut self, behavior: MissedTickBehavior) {
+        self.missed_tick_behavior = behavior;
+    }
+
+    // When the tick after the one scheduled for `scheduled` is due, given
+    // that it was seen at `now`
+    fn following(&self, scheduled: Instant, now: Instant) -> Instant {
+        let next = scheduled + self.period;
+        if next > now {
+            return next;
+        }
+
+        match self.missed_tick_behavior {
+            MissedTickBehavior::Burst => next,
+            MissedTickBehavior::Delay => now + self.period,
+            MissedTickBehavior::Skip => {
+                let period = self.period.as_nanos();
+                let missed = (now - scheduled).as_nanos() / period;
+                // In nanos, as the tick count overflows a u32 for short periods
+                let offset = u64::try_from((missed + 1) * period).unwrap_or(u64::MAX);
+                scheduled
+                    .checked_add(Duration::from_nanos(offset))
+                    .unwrap_or(now + self.period)
+            }
+        }
+    }
+}
+
+impl Stream for Interval {
+    type Item = Instant;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Instant>> {
+        let next = self.next;
+        let timer = self.timer.get_or_insert_with(|| TimerFuture::at(next));
+        match Pin::new(&mut *timer).poll(cx) {
+            Poll::Ready(_) => {
+                // The timer may have fired long before this poll; lateness
+                // counts from when the tick is seen
+                let following = self.following(next, clock::now());
+                // Re-armed rather than re-created, to keep its wheel entry
+                let timer = self.timer.as_mut().unwrap();
+                Pin::new(timer).reset(following);
+                self.next = following;
+                Poll::Ready(Some(next))
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PERIOD: Duration = Duration::from_millis(10);
+
+    fn late_interval(behavior: MissedTickBehavior) -> (Interval, Instant) {
+        let start = Instant::now();
+        let mut interval = interval_at(start, PERIOD);
+        interval.set_missed_tick_behavior(behavior);
+        (interval, start)
+    }
+
+    #[test]
+    fn on_time_ticks_keep_the_schedule() {
+        for behavior in [
+            MissedTickBehavior::Burst,
+            MissedTickBehavior::Delay,
+            MissedTickBehavior::Skip,
+        ] {
+            let (interval, start) = late_interval(behavior);
+            let now = start + Duration::from_millis(3);
+            assert_eq!(interval.following(start, now), start + PERIOD);
+        }
+    }
+
+    #[test]
+    fn burst_yields_missed_ticks() {
+        let (interval, start) = late_interval(MissedTickBehavior::Burst);
+        let now = start + Duration::from_millis(35);
+        assert_eq!(interval.following(start, now), start + PERIOD);
+    }
+
+    #[test]
+    fn delay_restarts_from_now() {
+        let (interval, start) = late_interval(MissedTickBehavior::Delay);
+        let now = start + Duration::from_millis(35);
+        assert_eq!(interval.following(start, now), now + PERIOD);
+    }
+
+    #[test]
+    fn skip_keeps_to_the_original_schedule() {
+        let (interval, start) = late_interval(MissedTickBehavior::Skip);
+        let now = start + Duration::from_millis(35);
+        assert_eq!(
+            interval.following(start, now),
+            start + Duration::from_millis(40)
+        );
+
+        // Exactly on a missed tick: the next one is a full period later
+        let now = start + Duration::from_millis(30);
+        assert_eq!(
+            interval.following(start, now),
+            start + Duration::from_millis(40)
+        );
+    }
+
+    #[test]
+    fn skip_does_not_truncate_many_missed_ticks() {
+        let start = Instant::now();
+        let mut interval = interval_at(start, Duration::from_nanos(1));
+        interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+        // More missed ticks than fit in a u32
+        let now = start + Duration::from_secs(5);
+        assert_eq!(
+            interval.following(start, now),
+            now + Duration::from_nanos(1)
+        );
+    }
+}
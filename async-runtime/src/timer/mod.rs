@@ -0,0 +1,185 @@
+use std::{
+    pin::Pin,
+    sync::{Condvar, Mutex, OnceLock},
+    task::{Context, Poll},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{clock, coop};
+
+mod interval;
+mod timeout;
+mod wheel;
+
+pub use interval::{Interval, MissedTickBehavior, interval, interval_at};
+pub use timeout::{Elapsed, Timeout, timeout, timeout_at};
+use wheel::{TimerKey, Wheel};
+
+// The timer thread and the wheel every timer future registers with
+struct Driver {
+    // Tick 0 of the wheel
+    origin: Instant,
+    wheel: Mutex<Wheel>,
+    // Signalled when a timer is inserted ahead of the thread's next wake-up
+    changed: Condvar,
+}
+
+impl Driver {
+    fn get() -> &'static Driver {
+        static DRIVER: OnceLock<&'static Driver> = OnceLock::new();
+
+        DRIVER.get_or_init(|| {
+            let driver: &'static Driver = Box::leak(Box::new(Driver {
+                origin: clock::now(),
+                wheel: Mutex::new(Wheel::new()),
+                changed: Condvar::new(),
+            }));
+            thread::Builder::new()
+                .name("timer".into())
+                .spawn(move || driver.run())
+                .expect("failed to start the timer thread");
+            driver
+        })
+    }
+
+    // The first tick at or after `instant`, so timers never fire early
+    fn tick_at(&self, instant: Instant) -> u64 {
+        let since_origin = instant.saturating_duration_since(self.origin);
+        since_origin.as_nanos().div_ceil(1_000_000) as u64
+    }
+
+    // The last tick that has fully passed
+    fn now(&self) -> u64 {
+        let elapsed = clock::now().saturating_duration_since(self.origin);
+        elapsed.as_millis() as u64
+    }
+
+    // Runs `f` on the wheel, waking the timer thread if that moved the
+    // next expiration
+    fn update<R>(&self, f: impl FnOnce(&mut Wheel) -> R) -> R {
+        let mut wheel = self.wheel.lock().unwrap();
+        let before = wheel.next_expiration();
+        let result = f(&mut wheel);
+        if wheel.next_expiration() != before {
+            self.changed.notify_one();
+        }
+        result
+    }
+
+    fn register(&self, deadline: Instant) -> TimerKey {
+        self.update(|wheel| wheel.insert(self.tick_at(deadline)))
+    }
+
+    fn run(&self) {
+        let mut wheel = self.wheel.lock().unwrap();
+        loop {
+            let now = self.now();
+            let wakers = wheel.advance(now, clock::now());
+            if !wakers.is_empty() {
+                drop(wheel);
+                wakers.into_iter().for_each(|waker| waker.wake());
+                wheel = self.wheel.lock().unwrap();
+                continue;
+            }
+
+            // A clock that does not move on its own wakes the thread when
+            // it is advanced
+            let expiration = wheel.next_expiration();
+            let wait = expiration
+                .and_then(|tick| clock::real_time_until(self.origin + Duration::from_millis(tick)));
+            wheel = match wait {
+                Some(wait) => self.changed.wait_timeout(wheel, wait).unwrap().0,
+                None => self.changed.wait(wheel).unwrap(),
+            };
+        }
+    }
+}
+
+// Fires the timers that are due after the clock jumped, and has the timer
+// thread recompute its wait
+pub(crate) fn clock_changed() {
+    let driver = Driver::get();
+    let wakers = {
+        let mut wheel = driver.wheel.lock().unwrap();
+        driver.changed.notify_one();
+        wheel.advance(driver.now(), clock::now())
+    };
+    wakers.into_iter().for_each(|waker| waker.wake());
+}
+
+/// Completes once its deadline has passed, with the time it fired at. All
+/// timers share one thread driving a timer wheel with 1 ms resolution;
+/// dropping a timer unregisters it.
+pub struct TimerFuture {
+    key: TimerKey,
+    deadline: Instant,
+}
+
+impl Future for TimerFuture {
+    type Output = Instant;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        coop::cooperative(cx, |cx| {
+            let mut wheel = Driver::get().wheel.lock().unwrap();
+
+            match wheel.poll(self.key, cx.waker()) {
+                Some(instant) => Poll::Ready(instant),
+                None => Poll::Pending,
+            }
+        })
+    }
+}
+
+impl TimerFuture {
+    pub fn new(duration: Duration) -> Self {
+        Self::at(clock::now() + duration)
+    }
+
+    /// A timer that fires at `deadline`, or right away if it has passed.
+    pub fn at(deadline: Instant) -> Self {
+        TimerFuture {
+            key: Driver::get().register(deadline),
+            deadline,
+        }
+    }
+
+    pub fn deadline(&self) -> Instant {
+        self.deadline
+    }
+
+    /// Whether the timer has fired.
+    pub fn is_elapsed(&self) -> bool {
+        Driver::get()
+            .wheel
+            .lock()
+            .unwrap()
+            .fired_at(self.key)
+            .is_some()
+    }
+
+    /// Moves the timer to `deadline`, even if it already fired; the task
+    /// awaiting it keeps waiting. Cheap enough to call on every event, e.g.
+    /// to kick a watchdog.
+    pub fn reset(mut self: Pin<&mut Self>, deadline: Instant) {
+        let driver = Driver::get();
+        driver.update(|wheel| wheel.reset(self.key, driver.tick_at(deadline)));
+        self.deadline = deadline;
+    }
+}
+
+/// Waits until `duration` has passed.
+pub fn sleep(duration: Duration) -> TimerFuture {
+    TimerFuture::new(duration)
+}
+
+/// Waits until `deadline`.
+pub fn sleep_until(deadline: Instant) -> TimerFuture {
+    TimerFuture::at(deadline)
+}
+
+impl Drop for TimerFuture {
+    fn drop(&mut self) {
+        Driver::get().wheel.lock().unwrap().remove(self.key);
+    }
+}
@@ -0,0 +1,320 @@
+use std::{task::Waker, time::Instant};
+
+// Each level has 64 slots, each covering 64 times the span of a slot on
+// the level below; level 0 slots are one tick (1 ms) wide. Six levels
+// cover 64^6 ms, a bit over two years; the top level wraps around, and
+// timers further out are parked in its last slot until they come closer.
+const SLOT_BITS: u32 = 6;
+const SLOTS: usize = 1 << SLOT_BITS;
+const LEVELS: usize = 6;
+const MAX_DELAY: u64 = 1 << (SLOT_BITS * LEVELS as u32);
+
+/// Identifies a timer registered with a `Wheel`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct TimerKey(usize);
+
+struct Entry {
+    deadline: u64,
+    waker: Option<Waker>,
+    fired_at: Option<Instant>,
+    // Level, slot and index within the slot, while pending
+    location: Option<(usize, usize, usize)>,
+}
+
+struct Level {
+    slots: Vec<Vec<usize>>,
+    // Bit i is set when slot i is non-empty
+    occupied: u64,
+}
+
+/// A hierarchical timer wheel with millisecond ticks. Inserting and
+/// cancelling a timer are O(1); firing a timer costs at most one move per
+/// level as it cascades down.
+pub(crate) struct Wheel {
+    // Ticks processed so far
+    elapsed: u64,
+    levels: Vec<Level>,
+
+    // Timers by key; a timer keeps its entry until it is removed, even
+    // after firing, so its owner can see that it fired
+    entries: Vec<Option<Entry>>,
+    free: Vec<usize>,
+}
+
+impl Wheel {
+    pub(crate) fn new() -> Self {
+        Wheel {
+            elapsed: 0,
+            levels: (0..LEVELS)
+                .map(|_| Level {
+                    slots: vec![Vec::new(); SLOTS],
+                    occupied: 0,
+                })
+                .collect(),
+            entries: Vec::new(),
+            free: Vec::new(),
+        }
+    }
+
+    fn entry(&mut self, key: TimerKey) -> &mut Entry {
+        self.entries[key.0]
+            .as_mut()
+            .expect("timer key used after removal")
+    }
+
+    /// Registers a timer firing at tick `deadline`. Deadlines already
+    /// reached fire on the next `advance`.
+    pub(crate) fn insert(&mut self, deadline: u64) -> TimerKey {
+        let entry = Entry {
+            deadline,
+            waker: None,
+            fired_at: None,
+            location: None,
+        };
+        let index = match self.free.pop() {
+            Some(index) => {
+                self.entries[index] = Some(entry);
+                index
+            }
+            None => {
+                self.entries.push(Some(entry));
+                self.entries.len() - 1
+            }
+        };
+        self.schedule(index);
+        TimerKey(index)
+    }
+
+    /// Unregisters a timer, fired or not.
+    pub(crate) fn remove(&mut self, key: TimerKey) {
+        self.unschedule(key.0);
+        self.entries[key.0] = None;
+        self.free.push(key.0);
+    }
+
+    /// Moves a timer, fired or not, to a new deadline. Its waker is kept.
+    pub(crate) fn reset(&mut self, key: TimerKey, deadline: u64) {
+        self.unschedule(key.0);
+        let entry = self.entry(key);
+        entry.deadline = deadline;
+        entry.fired_at = None;
+        self.schedule(key.0);
+    }
+
+    pub(crate) fn fired_at(&self, key: TimerKey) -> Option<Instant> {
+        self.entries[key.0].as_ref()?.fired_at
+    }
+
+    /// When the timer fired, if it has; otherwise stores `waker` to be
+    /// woken when it does.
+    pub(crate) fn poll(&mut self, key: TimerKey, waker: &Waker) -> Option<Instant> {
+        let entry = self.entry(key);
+        if entry.fired_at.is_none() && !entry.waker.as_ref().is_some_and(|w| w.will_wake(waker)) {
+            entry.waker = Some(waker.clone());
+        }
+        entry.fired_at
+    }
+
+    // Puts a pending entry in the slot its deadline falls in
+    fn schedule(&mut self, index: usize) {
+        let elapsed = self.elapsed;
+        let entry = self.entries[index].as_mut().unwrap();
+        // Past deadlines go in the current slot, which is processed next.
+        // Far ones are pulled in to just before the top level wraps back to
+        // the current slot; they cascade there and get rescheduled.
+        let top_shift = (LEVELS as u32 - 1) * SLOT_BITS;
+        let horizon = (elapsed >> top_shift << top_shift) + MAX_DELAY - 1;
+        let deadline = entry.deadline.clamp(elapsed, horizon);
+
+        // The level is given by the highest bit in which the deadline and
+        // the current tick differ
+        let masked = (elapsed ^ deadline) | (SLOTS as u64 - 1);
+        let level = (((63 - masked.leading_zeros()) / SLOT_BITS) as usize).min(LEVELS - 1);
+        let slot = ((deadline >> (level as u32 * SLOT_BITS)) as usize) & (SLOTS - 1);
+
+        let level_ref = &mut self.levels[level];
+        entry.location = Some((level, slot, level_ref.slots[slot].len()));
+        level_ref.slots[slot].push(index);
+        level_ref.occupied |= 1 << slot;
+    }
+
+    // Takes a pending entry out of its slot
+    fn unschedule(&mut self, index: usize) {
+        let Some((level, slot, position)) = self.entries[index].as_mut().unwrap().location.take()
+        else {
+            return;
+        };
+
+        let slot_entries = &mut self.levels[level].slots[slot];
+        slot_entries.swap_remove(position);
+        if let Some(&moved) = slot_entries.get(position) {
+            self.entries[moved].as_mut().unwrap().location = Some((level, slot, position));
+        }
+        if slot_entries.is_empty() {
+            self.levels[level].occupied &= !(1 << slot);
+        }
+    }
+
+    /// The tick at which the next slot has to be processed, if any timer is
+    /// pending. Timers in that slot may fire then, or move to a lower level.
+    pub(crate) fn next_expiration(&self) -> Option<u64> {
+        self.levels
+            .iter()
+            .enumerate()
+            .find_map(|(level, level_ref)| {
+                if level_ref.occupied == 0 {
+                    return None;
+                }
+                let shift = level as u32 * SLOT_BITS;
+                let current = ((self.elapsed >> shift) as usize) & (SLOTS - 1);
+                // Occupied slots never lie behind the current one, except on
+                // the top level, where they belong to its next turn
+                let ahead = level_ref.occupied.rotate_right(current as u32);
+                let slots_ahead = ahead.trailing_zeros() as u64;
+
+                let slot_start = (self.elapsed >> shift) + slots_ahead;
+                Some((slot_start << shift).max(self.elapsed))
+            })
+    }
+
+    /// Advances to tick `now`, firing every timer whose deadline has been
+    /// reached. Returns the wakers of the timers that fired.
+    pub(crate) fn advance(&mut self, now: u64, fired_at: Instant) -> Vec<Waker> {
+        let mut wakers = Vec::new();
+
+        while let Some(expiration) = self.next_expiration()
+            && expiration <= now
+        {
+            self.elapsed = expiration;
+            for level in 0..LEVELS {
+                let shift = level as u32 * SLOT_BITS;
+                let slot = ((expiration >> shift) as usize) & (SLOTS - 1);
+                if self.levels[level].occupied & (1 << slot) == 0 {
+                    continue;
+                }
+                // A slot on a higher level is only due once its start is reached
+                if level > 0 && expiration & ((1 << shift) - 1) != 0 {
+                    continue;
+                }
+
+                let indices = std::mem::take(&mut self.levels[level].slots[slot]);
+                self.levels[level].occupied &= !(1 << slot);
+                for index in indices {
+                    let entry = self.entries[index].as_mut().unwrap();
+                    entry.location = None;
+                    if entry.deadline <= expiration {
+                        entry.fired_at = Some(fired_at);
+                        wakers.extend(entry.waker.take());
+                    } else {
+                        // Cascade to a lower level
+                        self.schedule(index);
+                    }
+                }
+            }
+        }
+        self.elapsed = self.elapsed.max(now);
+
+        wakers
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::task::noop_waker_ref;
+
+    use super::*;
+
+    // Advances to `deadline - 1`, then to `deadline`, checking that the
+    // timer fires on the second step only
+    fn assert_fires_at(wheel: &mut Wheel, key: TimerKey, deadline: u64) {
+        let now = Instant::now();
+        wheel.advance(deadline - 1, now);
+        assert_eq!(wheel.fired_at(key), None, "fired before {}", deadline);
+        wheel.advance(deadline, now);
+        assert_eq!(
+            wheel.fired_at(key),
+            Some(now),
+            "did not fire at {}",
+            deadline
+        );
+    }
+
+    #[test]
+    fn insert_and_remove() {
+        let mut wheel = Wheel::new();
+        let key = wheel.insert(10);
+        assert_eq!(wheel.next_expiration(), Some(10));
+
+        assert!(wheel.poll(key, noop_waker_ref()).is_none());
+        wheel.remove(key);
+        assert_eq!(wheel.next_expiration(), None);
+        assert!(wheel.advance(20, Instant::now()).is_empty());
+
+        // The key is reused
+        assert_eq!(wheel.insert(30), key);
+    }
+
+    #[test]
+    fn fired_timer_wakes_its_waker() {
+        let mut wheel = Wheel::new();
+        let key = wheel.insert(5);
+        wheel.poll(key, noop_waker_ref());
+
+        assert!(wheel.advance(4, Instant::now()).is_empty());
+        assert_eq!(wheel.advance(5, Instant::now()).len(), 1);
+        assert!(wheel.poll(key, noop_waker_ref()).is_some());
+    }
+
+    #[test]
+    fn cascades_across_slot_boundaries() {
+        let deadlines = [
+            1, 63, 64, 65, 4095, 4096, 4097, 262_143, 262_144, 20_000_000,
+        ];
+        let mut wheel = Wheel::new();
+        let keys: Vec<_> = deadlines.iter().map(|&d| wheel.insert(d)).collect();
+
+        for (&key, &deadline) in keys.iter().zip(&deadlines) {
+            assert_fires_at(&mut wheel, key, deadline);
+        }
+        assert_eq!(wheel.next_expiration(), None);
+    }
+
+    #[test]
+    fn past_deadline_fires_on_next_advance() {
+        let mut wheel = Wheel::new();
+        wheel.advance(100, Instant::now());
+
+        let key = wheel.insert(50);
+        assert_eq!(wheel.next_expiration(), Some(100));
+        wheel.advance(100, Instant::now());
+        assert!(wheel.fired_at(key).is_some());
+    }
+
+    #[test]
+    fn reset_moves_timer() {
+        let mut wheel = Wheel::new();
+        let key = wheel.insert(10);
+        wheel.reset(key, 1000);
+        assert_fires_at(&mut wheel, key, 1000);
+
+        // A fired timer can be armed again
+        wheel.reset(key, 5000);
+        assert_eq!(wheel.fired_at(key), None);
+        assert_fires_at(&mut wheel, key, 5000);
+    }
+
+    #[test]
+    fn far_future_deadlines() {
+        let mut wheel = Wheel::new();
+        let never = wheel.insert(u64::MAX);
+        let far = wheel.insert(3 * MAX_DELAY + 5);
+        assert_fires_at(&mut wheel, far, 3 * MAX_DELAY + 5);
+
+        // Inserted on the last tick before the top level wraps
+        wheel.advance(4 * MAX_DELAY - 1, Instant::now());
+        let next_turn = wheel.insert(5 * MAX_DELAY);
+        assert_fires_at(&mut wheel, next_turn, 5 * MAX_DELAY);
+
+        assert_eq!(wheel.fired_at(never), None);
+    }
+}
@@ -0,0 +1,180 @@
+use std::{
+    cell::RefCell,
+    iter,
+    sync::{
+        Arc, Condvar, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread::{self, JoinHandle},
+};
+
+use crossbeam::deque::{Injector, Steal, Stealer, Worker};
+
+use crate::{
+    executor::poll_task,
+    metrics::Metrics,
+    power::Activity,
+    task::{RunQueue, Scheduler, Spawner, Task},
+};
+
+// Queues shared by every worker of one pool
+struct Shared {
+    // Tasks scheduled from outside the pool (or before a worker picked them up)
+    injector: Injector<Arc<Task>>,
+    // One stealer per worker-local queue
+    stealers: Vec<Stealer<Arc<Task>>>,
+
+    // Number of workers currently parked, guarded for the condvar
+    sleeping: Mutex<usize>,
+    wakeup: Condvar,
+
+    // Set once all spawners and tasks are gone; workers exit when idle
+    closed: AtomicBool,
+
+    // Idle time is summed over all workers
+    metrics: Arc<Metrics>,
+}
+
+struct WorkerContext {
+    shared: Arc<Shared>,
+    local: Worker<Arc<Task>>,
+}
+
+thread_local! {
+    // Set on worker threads so that wakes from inside a task go to the
+    // worker's own queue instead of the global injector
+    static CURRENT_WORKER: RefCell<Option<WorkerContext>> = const { RefCell::new(None) };
+}
+
+struct WorkStealingQueue {
+    shared: Arc<Shared>,
+}
+
+impl RunQueue for WorkStealingQueue {
+    fn push(&self, task: Arc<Task>) {
+        if self.shared.closed.load(Ordering::Acquire) {
+            return;
+        }
+
+        let task = CURRENT_WORKER.with(|current| match &*current.borrow() {
+            Some(context) if Arc::ptr_eq(&context.shared, &self.shared) => {
+                context.local.push(task);
+                None
+            }
+            _ => Some(task),
+        });
+        if let Some(task) = task {
+            self.shared.injector.push(task);
+        }
+
+        self.shared.notify_one();
+    }
+
+    fn close(&self) {
+        self.shared.closed.store(true, Ordering::Release);
+
+        let _sleeping = self.shared.sleeping.lock().unwrap();
+        self.shared.wakeup.notify_all();
+    }
+}
+
+impl Shared {
+    fn notify_one(&self) {
+        let sleeping = self.sleeping.lock().unwrap();
+        if *sleeping > 0 {
+            self.wakeup.notify_one();
+        }
+    }
+
+    fn has_work(&self) -> bool {
+        !self.injector.is_empty() || self.stealers.iter().any(|stealer| !stealer.is_empty())
+    }
+
+    // Local queue first, then a batch from the injector, then other workers
+    fn find_task(&self, local: &Worker<Arc<Task>>) -> Option<Arc<Task>> {
+        local.pop().or_else(|| {
+            iter::repeat_with(|| {
+                self.injector.steal_batch_and_pop(local).or_else(|| {
+                    self.stealers
+                        .iter()
+                        .map(|stealer| stealer.steal())
+                        .collect()
+                })
+            })
+            .find(|steal| !steal.is_retry())
+            .and_then(Steal::success)
+        })
+    }
+
+    fn park(&self, activity: &mut Activity) {
+        let mut sleeping = self.sleeping.lock().unwrap();
+        // Re-check under the lock so a concurrent schedule can't be missed
+        if self.has_work() || self.closed.load(Ordering::Acquire) {
+            return;
+        }
+        *sleeping += 1;
+        sleeping = activity.sleep(|| self.wakeup.wait(sleeping).unwrap());
+        *sleeping -= 1;
+    }
+}
+
+fn worker_loop(shared: Arc<Shared>, local: Worker<Arc<Task>>) {
+    CURRENT_WORKER.with(|current| {
+        *current.borrow_mut() = Some(WorkerContext {
+            shared: shared.clone(),
+            local,
+        });
+    });
+
+    let mut activity = Activity::new(shared.metrics.clone());
+    activity.resume();
+    loop {
+        // The borrow must end before polling, since the task may reschedule itself
+        let task = CURRENT_WORKER.with(|current| {
+            let current = current.borrow();
+            let context = current.as_ref().unwrap();
+            shared.find_task(&context.local)
+        });
+
+        match task {
+            Some(task) => poll_task(&task),
+            None if shared.closed.load(Ordering::Acquire) => break,
+            None => shared.park(&mut activity),
+        }
+    }
+    activity.suspend();
+}
+
+/// A sensible default worker count: one per available CPU.
+pub fn default_worker_count() -> usize {
+    thread::available_parallelism().map_or(1, |n| n.get())
+}
+
+/// Starts `num_workers` executor threads that share tasks through per-worker
+/// run queues and work stealing. Tasks are spawned with the usual `Spawner`;
+/// the workers exit once it is dropped and every task has finished.
+pub fn spawn_work_stealing_executor(num_workers: usize) -> (Vec<JoinHandle<()>>, Spawner) {
+    assert!(num_workers > 0, "need at least one worker");
+
+    let locals: Vec<_> = (0..num_workers).map(|_| Worker::new_fifo()).collect();
+    let shared = Arc::new(Shared {
+        injector: Injector::new(),
+        stealers: locals.iter().map(Worker::stealer).collect(),
+        sleeping: Mutex::new(0),
+        wakeup: Condvar::new(),
+        closed: AtomicBool::new(false),
+        metrics: Metrics::new(),
+    });
+
+    let handles = locals
+        .into_iter()
+        .map(|local| {
+            let shared = shared.clone();
+            thread::spawn(move || worker_loop(shared, local))
+        })
+        .collect();
+
+    let metrics = shared.metrics.clone();
+    let scheduler = Scheduler::new(WorkStealingQueue { shared }, metrics);
+    (handles, Spawner::new(scheduler))
+}
@@ -12,7 +12,7 @@ use async_runtime::{
         radio::{Radio, RadioFuture, RadioFutureCreateArg, RadioInputMsg},
     },
 };
-use secrets_structs::{LabelNonIdem, LabelTimely, Labeled};
+use secrets_structs::LabelTimely;
 
 async fn foo() {
     let karma = Karma::new(Radio::new(1));
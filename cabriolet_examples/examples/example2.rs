@@ -0,0 +1,54 @@
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicU32, Ordering},
+    },
+    time::Duration,
+};
+
+use futures::FutureExt;
+
+use async_runtime::{
+    clock::{self, VirtualClock},
+    executor::Runtime,
+    timer::TimerFuture,
+};
+use secrets_structs::{LabelNonIdem, LabelTimely, Labeled, TimelyClosure};
+
+fn main() {
+    // Time only moves when the clock is advanced
+    let clock = VirtualClock::new();
+    clock::set_clock(clock.clone());
+
+    let runtime = Runtime::new();
+    let spawner = runtime.spawner();
+
+    runtime.block_on(async move {
+        let computed = Arc::new(AtomicU32::new(0));
+        let counter = computed.clone();
+        let recompute: TimelyClosure<u32> = Arc::new(move || {
+            let counter = counter.clone();
+            async move { counter.fetch_add(1, Ordering::SeqCst) + 1 }.boxed()
+        });
+        let mut reading: Labeled<u32, LabelTimely<100>> = Labeled::new(recompute);
+
+        assert_eq!(reading.unwrap_checked::<LabelNonIdem>().await, 1);
+
+        // Still fresh: the cached value is returned
+        clock.advance(Duration::from_millis(60));
+        assert_eq!(reading.unwrap_checked::<LabelNonIdem>().await, 1);
+
+        // Expired: recomputed
+        clock.advance(Duration::from_millis(60));
+        assert_eq!(reading.unwrap_checked::<LabelNonIdem>().await, 2);
+        println!("value computed {} times", computed.load(Ordering::SeqCst));
+
+        // A ten minute timer fires as soon as the clock gets there
+        let start = clock::now();
+        let timer = spawner.spawn(TimerFuture::new(Duration::from_secs(600)));
+        clock.advance(Duration::from_secs(599));
+        clock.advance(Duration::from_secs(1));
+        let fired = timer.await.unwrap();
+        println!("timer fired after {:?}", fired - start);
+    });
+}
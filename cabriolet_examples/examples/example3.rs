@@ -0,0 +1,48 @@
+use std::{
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use futures::FutureExt;
+
+use async_runtime::{
+    clock,
+    executor::Runtime,
+    karma::{
+        Karma, Peripheral,
+        rtc::{Rtc, RtcInputMsg, RtcState},
+    },
+};
+use secrets_structs::{LabelNonIdem, LabelTimely, Labeled, TimelyClosure};
+
+fn main() {
+    // The RTC's registers outlive this process; run the example twice to
+    // see it keep counting in between
+    let path = std::env::temp_dir().join("cabriolet-rtc.json");
+    let rtc = Rtc::open(1, &path).unwrap();
+    let mut karma = Karma::new(rtc.clone());
+    if rtc.get_current_state() == RtcState::Stopped {
+        let host_time = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+        karma.send(RtcInputMsg::Start).unwrap();
+        karma.send(RtcInputMsg::SetTime(host_time)).unwrap();
+        println!("RTC started");
+    }
+    println!("RTC reads {:?}", rtc.now());
+    clock::set_wall_clock(rtc.clone());
+
+    let runtime = Runtime::new();
+    runtime.block_on(async move {
+        let recompute: TimelyClosure<Duration> = Arc::new(|| async { clock::timestamp() }.boxed());
+        let mut reading: Labeled<Duration, LabelTimely<1000>> = Labeled::new(recompute);
+        let first = reading.unwrap_checked::<LabelNonIdem>().await;
+
+        // Power off for 1.5s; the reading survives in memory, and the RTC
+        // counts through the outage
+        let mut rtc = rtc;
+        std::thread::sleep(Duration::from_millis(1500));
+        rtc.power_cycle();
+
+        let second = reading.unwrap_checked::<LabelNonIdem>().await;
+        println!("reading expired across the outage: {}", second != first);
+    });
+}
@@ -0,0 +1,60 @@
+use std::{sync::Arc, time::Duration};
+
+use futures::FutureExt;
+
+use async_runtime::{
+    executor::Runtime,
+    sensor::{PlaybackOptions, Sensor, Trace},
+    sync::Mutex,
+    temperature_sensor::{Temperature, TemperatureSensor},
+    timer::TimerFuture,
+};
+use secrets_structs::{LabelNonIdem, LabelTimely, Labeled, TimelyClosure};
+
+const TRACE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/traces/greenhouse.csv");
+
+// Replays the recording, reading it through a value that stays fresh for
+// TIME ms, and returns how often the value was recomputed
+async fn run<const TIME: u64>(trace: Trace<Temperature>) -> u32 {
+    // Recorded every 10s, replayed 40 times faster
+    let options = PlaybackOptions {
+        speed: 40.0,
+        ..PlaybackOptions::default()
+    };
+    let sensor = Arc::new(Mutex::new(TemperatureSensor::from_trace(trace, options)));
+
+    let recompute: TimelyClosure<Option<Temperature>> = Arc::new(move || {
+        let sensor = sensor.clone();
+        async move { sensor.lock().await.read().await.last().copied() }.boxed()
+    });
+    let mut temperature: Labeled<_, LabelTimely<TIME>> = Labeled::new(recompute);
+
+    let mut recomputed = 0;
+    let mut last = None;
+    while let Some(reading) = temperature.unwrap_checked::<LabelNonIdem>().await {
+        if last != Some(reading) {
+            recomputed += 1;
+            last = Some(reading);
+        }
+        TimerFuture::new(Duration::from_millis(100)).await;
+    }
+    recomputed
+}
+
+fn main() {
+    let trace = Trace::from_csv(TRACE).unwrap();
+    println!(
+        "{} samples over {:?}",
+        trace.samples().len(),
+        trace.duration()
+    );
+
+    let runtime = Runtime::new();
+    runtime.block_on(async move {
+        // The same recording through two freshness windows
+        let fresh = run::<250>(trace.clone()).await;
+        println!("250ms window: saw {} distinct readings", fresh);
+        let stale = run::<1000>(trace).await;
+        println!("1000ms window: saw {} distinct readings", stale);
+    });
+}
@@ -8,22 +8,37 @@ use std::{
 use futures::FutureExt;
 use some_macros::labeled_block;
 
-use async_runtime::{executor::spawn_executor_thread, timer::TimerFuture};
+use async_runtime::{
+    blocking::spawn_blocking,
+    coop::LongPollPolicy,
+    deadline::with_deadline,
+    executor::{Runtime, SchedulingPolicy},
+    task_local,
+    timer::TimerFuture,
+};
 use secrets_structs::{LabelNonIdem, LabelTimely, Labeled};
 
+task_local! {
+    static NODE_ID: u32;
+}
+
 async fn foo() {
+    NODE_ID.set(1);
     let x = 1;
 
     let before = Instant::now();
-    let y = labeled_block!(LabelTimely<10000> || {
+    let mut y = labeled_block!(LabelTimely<10000> || {
         let timer_future = TimerFuture::new(Duration::from_millis(3000));
         timer_future.await;
 
         let now = Instant::now();
         let elapsed = now - before;
+        println!("node {}: block took {:?}", NODE_ID.get(), elapsed);
 
         elapsed.as_millis() as i32
     });
+    // Blocks capture a copy of `y`, so compute it once up front
+    println!("y: {:?}", y.unwrap_checked::<LabelNonIdem>().await);
 
     let n: Labeled<i32, LabelTimely<100>> = labeled_block!(LabelTimely<100> |y| {
         let yp = unwrap_labeled(y);
@@ -34,7 +49,9 @@ async fn foo() {
     println!("n: {:?}", n.endorse_idempotent().await);
 
     let z = labeled_block!(LabelTimely<100> |y| {
-        std::thread::sleep(std::time::Duration::from_millis(1000));
+        // Keeps y's deadline on this task while it waits, so EDF sees it
+        let work = spawn_blocking(|| std::thread::sleep(Duration::from_millis(1000)));
+        with_deadline(y.deadline(), work).await.unwrap();
 
         let sigma = x + unwrap_labeled(y);
 
@@ -45,7 +62,7 @@ async fn foo() {
 
     let w = labeled_block!(
         LabelNonIdem | y | {
-            std::thread::sleep(std::time::Duration::from_millis(1000));
+            spawn_blocking(|| std::thread::sleep(Duration::from_millis(1000))).await.unwrap();
 
             let sigma = x + unwrap_labeled(y);
 
@@ -57,13 +74,16 @@ async fn foo() {
 }
 
 fn main() {
-    let (handle, spawner) = spawn_executor_thread();
-
-    spawner.spawn(async {
-        foo().await;
+    let mut runtime = Runtime::new();
+    runtime.set_scheduling_policy(SchedulingPolicy::EarliestDeadlineFirst);
+    // Flags blocking calls that slipped into async code
+    runtime.set_long_poll_policy(LongPollPolicy {
+        log: true,
+        ..LongPollPolicy::default()
     });
 
-    drop(spawner);
+    let metrics = runtime.metrics();
+    runtime.block_on(foo());
 
-    handle.join().unwrap();
+    println!("deadline stats: {:?}", metrics.deadline_stats());
 }
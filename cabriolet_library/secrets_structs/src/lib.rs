@@ -1,10 +1,12 @@
 #![feature(generic_const_exprs)]
+#![allow(incomplete_features)]
 
 use std::{
     sync::Arc,
     time::{Duration, Instant},
 };
 
+use async_runtime::{clock, deadline::with_deadline};
 use async_trait::async_trait;
 use futures::future::BoxFuture;
 
@@ -24,7 +26,9 @@ pub trait AtMostAsIdemAs<T>: Label {}
 //impl<T: Label> AtMostAsIdemAs<T> for T {} // reflexive property
 
 impl<const TIME: u64> Label for LabelTimely<TIME> {
-    type MetaData<T> = (Instant, TimelyClosure<T>);
+    // Expiry as a wall-clock timestamp, which unlike an `Instant` still
+    // means something after a power failure
+    type MetaData<T> = (Duration, TimelyClosure<T>);
 }
 impl Label for LabelNonIdem {
     type MetaData<T> = ();
@@ -43,6 +47,16 @@ pub trait Contains<T> {
 
     fn new(args: Self::CreationArgs) -> Self;
 
+    /// When the contained value stops being usable, if ever. `None` once it
+    /// has expired, since it is then recomputed rather than used late.
+    fn deadline(&self) -> Option<Instant> {
+        None
+    }
+
+    /// # Safety
+    ///
+    /// Skips the label check performed by `Labeled::unwrap_checked`; the
+    /// caller must ensure the value may flow into the current context.
     async unsafe fn unwrap_unchecked(&mut self) -> T;
 }
 
@@ -85,19 +99,24 @@ impl<T: Clone + Send, const TIME: u64> Contains<T> for Labeled<T, LabelTimely<TI
     fn new(create_fn: Self::CreationArgs) -> Self {
         Self {
             val: None,
-            metadata: (Instant::now() + Duration::from_millis(TIME), create_fn),
+            metadata: (clock::timestamp() + Duration::from_millis(TIME), create_fn),
         }
     }
 
+    fn deadline(&self) -> Option<Instant> {
+        let expiry = self.metadata.0;
+        (self.val.is_some() && clock::timestamp() < expiry).then(|| clock::instant_at(expiry))
+    }
+
     async unsafe fn unwrap_unchecked(&mut self) -> T {
-        let now = Instant::now();
+        let now = clock::timestamp();
         let (expiry, ref create_fn) = self.metadata;
         if now < expiry && self.val.is_some() {
             self.val.clone().unwrap()
         } else {
             let val = create_fn().await;
             self.val = Some(val.clone());
-            self.metadata.0 = Instant::now() + Duration::from_millis(TIME);
+            self.metadata.0 = clock::timestamp() + Duration::from_millis(TIME);
 
             val
         }
@@ -119,10 +138,80 @@ where
     where
         Lp: AtMostAsIdemAs<L>,
     {
-        unsafe { self.unwrap_unchecked() }.await
+        let deadline = self.deadline();
+        with_deadline(deadline, unsafe { self.unwrap_unchecked() }).await
+    }
+
+    /// The deadline a task should hold while it waits to use this value.
+    /// Pass it to `with_deadline` around awaits that come before the
+    /// unwrap, so the EDF queue sees it while the task is suspended.
+    pub fn deadline(&self) -> Option<Instant> {
+        <Self as Contains<T>>::deadline(self)
     }
 
     pub async fn endorse_idempotent(mut self) -> T {
         self.unwrap_checked::<LabelNonIdem>().await
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        Mutex, MutexGuard, OnceLock,
+        atomic::{AtomicUsize, Ordering},
+    };
+
+    use async_runtime::clock::{VirtualClock, set_clock};
+    use futures::{FutureExt, executor::block_on};
+
+    use super::*;
+
+    // The clock is process-wide, so tests install one and take turns with it
+    fn virtual_clock() -> (VirtualClock, MutexGuard<'static, ()>) {
+        static CLOCK: OnceLock<VirtualClock> = OnceLock::new();
+        static TURN: Mutex<()> = Mutex::new(());
+
+        let turn = TURN.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let clock = CLOCK.get_or_init(|| {
+            let clock = VirtualClock::new();
+            set_clock(clock.clone());
+            clock
+        });
+        (clock.clone(), turn)
+    }
+
+    // A value that counts how many times it has been computed
+    fn counted() -> (Labeled<usize, LabelTimely<100>>, Arc<AtomicUsize>) {
+        let computed = Arc::new(AtomicUsize::new(0));
+        let counter = computed.clone();
+        let create_fn: TimelyClosure<usize> = Arc::new(move || {
+            let count = counter.fetch_add(1, Ordering::SeqCst) + 1;
+            async move { count }.boxed()
+        });
+        (Labeled::new(create_fn), computed)
+    }
+
+    #[test]
+    fn timely_value_is_reused_before_expiry() {
+        let (clock, _turn) = virtual_clock();
+        let (mut labeled, computed) = counted();
+
+        assert_eq!(block_on(labeled.unwrap_checked::<LabelNonIdem>()), 1);
+        clock.advance(Duration::from_millis(99));
+        assert!(labeled.deadline().is_some());
+        assert_eq!(block_on(labeled.unwrap_checked::<LabelNonIdem>()), 1);
+        assert_eq!(computed.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn timely_value_is_recomputed_after_expiry() {
+        let (clock, _turn) = virtual_clock();
+        let (mut labeled, computed) = counted();
+
+        assert_eq!(block_on(labeled.unwrap_checked::<LabelNonIdem>()), 1);
+        clock.advance(Duration::from_millis(101));
+        assert_eq!(labeled.deadline(), None);
+        assert_eq!(block_on(labeled.unwrap_checked::<LabelNonIdem>()), 2);
+        assert_eq!(computed.load(Ordering::SeqCst), 2);
+    }
+}
@@ -1,7 +1,6 @@
 extern crate proc_macro;
 use proc_macro::TokenStream;
 use quote::{ToTokens, quote};
-use syn;
 use syn::parse::{Parse, ParseStream};
 use syn::{Block, Expr, Ident, Stmt, Token, Type, parse_macro_input, punctuated::Punctuated};
 // use syn::parse::{Parse, ParseStream};
@@ -20,8 +19,8 @@ fn comma_separate<T: Iterator<Item = proc_macro2::TokenStream>>(ts: T) -> proc_m
             if acc.is_empty() {
                 token
             } else {
-                let ba: proc_macro2::TokenStream = acc.into();
-                let bt: proc_macro2::TokenStream = token.into();
+                let ba: proc_macro2::TokenStream = acc;
+                let bt: proc_macro2::TokenStream = token;
                 quote! {#ba, #bt}
             }
         },
@@ -33,7 +32,7 @@ fn is_call_to(call: &syn::ExprCall, path: &str) -> bool {
     if let Expr::Path(path_expr) = &*call.func {
         let mut path_str = quote::quote! {#path_expr}.to_string();
         path_str.retain(|c| !c.is_whitespace());
-        return path_str == path;
+        path_str == path
     } else {
         false
     }
@@ -41,10 +40,10 @@ fn is_call_to(call: &syn::ExprCall, path: &str) -> bool {
 
 // Returns whether the Type is a specific type
 fn is_type(the_type: &Type, path: &str) -> bool {
-    if let Type::Path(path_expr) = &*the_type {
+    if let Type::Path(path_expr) = the_type {
         let mut path_str = quote::quote! {#path_expr}.to_string();
         path_str.retain(|c| !c.is_whitespace());
-        return path_str == path;
+        path_str == path
     } else {
         false
     }
@@ -73,15 +72,12 @@ fn expand_expr(input: &Expr, label_type: &Type) -> TokenStream {
         }
         Expr::Binary(binary_expr) => {
             let mut new_binary_expr = binary_expr.clone();
-            new_binary_expr.left =
-                syn::parse(expand_expr(&binary_expr.left, label_type).into()).unwrap();
+            new_binary_expr.left = syn::parse(expand_expr(&binary_expr.left, label_type)).unwrap();
             new_binary_expr.right =
-                syn::parse(expand_expr(&binary_expr.right, label_type).into()).unwrap();
+                syn::parse(expand_expr(&binary_expr.right, label_type)).unwrap();
             new_binary_expr.into_token_stream().into()
         }
-        Expr::Lit(lit_expr) => match &lit_expr.lit {
-            _ => lit_expr.into_token_stream().into(),
-        },
+        Expr::Lit(lit_expr) => lit_expr.into_token_stream().into(),
         _ => input.into_token_stream().into(),
     }
 }
@@ -97,32 +93,29 @@ fn expand_block(input: &Block, label_type: &Type) -> TokenStream {
                         let mut new_local_expr = local_expr.clone();
                         let mut new_init = local_init.clone();
                         new_init.expr =
-                            syn::parse(expand_expr(&local_init.expr, label_type).into()).unwrap();
-                        new_init.diverge = match &local_init.diverge {
-                            Some((else_, diverge_expr)) => Some((
-                                *else_,
-                                syn::parse(expand_expr(&diverge_expr, label_type).into()).unwrap(),
-                            )),
-                            None => None,
-                        };
+                            syn::parse(expand_expr(&local_init.expr, label_type)).unwrap();
+                        new_init.diverge =
+                            local_init.diverge.as_ref().map(|(else_, diverge_expr)| {
+                                (
+                                    *else_,
+                                    syn::parse(expand_expr(diverge_expr, label_type)).unwrap(),
+                                )
+                            });
                         new_local_expr.init = Some(new_init);
-                        new_local_expr.into_token_stream().into()
+                        new_local_expr.into_token_stream()
                     }
-                    None => local_expr.into_token_stream().into(),
+                    None => local_expr.into_token_stream(),
                 },
-                Stmt::Item(item) => item.into_token_stream().into(),
+                Stmt::Item(item) => item.into_token_stream(),
                 Stmt::Expr(expr, maybe_token) => {
                     let mut expr_stream: proc_macro2::TokenStream =
-                        expand_expr(&expr, label_type).into();
-                    match maybe_token {
-                        Some(semi) => {
-                            expr_stream.extend(semi.into_token_stream());
-                        }
-                        None => {}
+                        expand_expr(expr, label_type).into();
+                    if let Some(semi) = maybe_token {
+                        expr_stream.extend(semi.into_token_stream());
                     }
                     expr_stream
                 }
-                Stmt::Macro(macro_expr) => macro_expr.into_token_stream().into(),
+                Stmt::Macro(macro_expr) => macro_expr.into_token_stream(),
             }
         })
         .collect();